@@ -0,0 +1,23 @@
+// Helpers shared by the benchmarks of the puzzles.
+
+// Runs `f` once and returns its result with the time it took.
+pub fn time<T>(f: impl FnOnce() -> T) -> (T, std::time::Duration) {
+  let before = std::time::Instant::now();
+  let result = f();
+  (result, before.elapsed())
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn returns_the_result() {
+    let (result, elapsed) = time(|| {
+      std::thread::sleep(std::time::Duration::from_millis(1));
+      42
+    });
+    assert_eq!(result, 42);
+    assert!(elapsed >= std::time::Duration::from_millis(1));
+  }
+}
@@ -0,0 +1,295 @@
+// Two dimensional grids indexed by integer points, y growing downwards.
+
+use std::collections::HashMap;
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug, Default)]
+pub struct Point {
+  pub x: i32,
+  pub y: i32,
+}
+
+impl Point {
+  pub fn new(x: i32, y: i32) -> Point {
+    Point { x, y }
+  }
+
+  // Up, right, down and left of this point.
+  pub fn neighbours(&self) -> [Point; 4] {
+    [
+      Point::new(self.x, self.y - 1),
+      Point::new(self.x + 1, self.y),
+      Point::new(self.x, self.y + 1),
+      Point::new(self.x - 1, self.y),
+    ]
+  }
+
+  // The four neighbours plus the four diagonals, clockwise from the top left.
+  pub fn surroundings(&self) -> [Point; 8] {
+    [
+      Point::new(self.x - 1, self.y - 1),
+      Point::new(self.x, self.y - 1),
+      Point::new(self.x + 1, self.y - 1),
+      Point::new(self.x + 1, self.y),
+      Point::new(self.x + 1, self.y + 1),
+      Point::new(self.x, self.y + 1),
+      Point::new(self.x - 1, self.y + 1),
+      Point::new(self.x - 1, self.y),
+    ]
+  }
+}
+
+impl std::ops::Add for Point {
+  type Output = Point;
+
+  fn add(self, other: Point) -> Point {
+    Point::new(self.x + other.x, self.y + other.y)
+  }
+}
+
+// Inclusive rectangle of points.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct Bounds {
+  pub top_left: Point,
+  pub bottom_right: Point,
+}
+
+impl Bounds {
+  pub fn from_point(point: Point) -> Bounds {
+    Bounds {
+      top_left: point,
+      bottom_right: point,
+    }
+  }
+
+  pub fn extend(&mut self, point: Point) {
+    self.top_left.x = self.top_left.x.min(point.x);
+    self.top_left.y = self.top_left.y.min(point.y);
+    self.bottom_right.x = self.bottom_right.x.max(point.x);
+    self.bottom_right.y = self.bottom_right.y.max(point.y);
+  }
+
+  pub fn contains(&self, point: Point) -> bool {
+    point.x >= self.top_left.x
+      && point.x <= self.bottom_right.x
+      && point.y >= self.top_left.y
+      && point.y <= self.bottom_right.y
+  }
+
+  pub fn width(&self) -> usize {
+    (self.bottom_right.x - self.top_left.x + 1) as usize
+  }
+
+  pub fn height(&self) -> usize {
+    (self.bottom_right.y - self.top_left.y + 1) as usize
+  }
+
+  // Offset of a point from the top left corner, for raster outputs.
+  pub fn offset(&self, point: Point) -> (usize, usize) {
+    (
+      (point.x - self.top_left.x) as usize,
+      (point.y - self.top_left.y) as usize,
+    )
+  }
+
+  // Every point, row by row.
+  pub fn points(&self) -> impl Iterator<Item = Point> {
+    let Bounds {
+      top_left,
+      bottom_right,
+    } = *self;
+    (top_left.y..=bottom_right.y)
+      .flat_map(move |y| (top_left.x..=bottom_right.x).map(move |x| Point::new(x, y)))
+  }
+}
+
+pub trait Grid<T> {
+  fn get(&self, point: Point) -> Option<&T>;
+
+  fn set(&mut self, point: Point, value: T);
+
+  // Smallest rectangle holding every cell set so far, None while empty.
+  fn bounds(&self) -> Option<Bounds>;
+
+  // Cells set around a point, in the order of `Point::neighbours`.
+  fn neighbours(&self, point: Point) -> Vec<(Point, &T)> {
+    point
+      .neighbours()
+      .iter()
+      .filter_map(|&neighbour| self.get(neighbour).map(|value| (neighbour, value)))
+      .collect()
+  }
+
+  /*
+   * Rendering hook: maps every point of `bounds`, set or not, to whatever the
+   * output needs (characters, colors...) and returns the rows.
+   */
+  fn render<U, F>(&self, bounds: &Bounds, mut f: F) -> Vec<Vec<U>>
+  where
+    F: FnMut(Point, Option<&T>) -> U,
+  {
+    (bounds.top_left.y..=bounds.bottom_right.y)
+      .map(|y| {
+        (bounds.top_left.x..=bounds.bottom_right.x)
+          .map(|x| {
+            let point = Point::new(x, y);
+            f(point, self.get(point))
+          })
+          .collect()
+      })
+      .collect()
+  }
+
+  // Text rendering of the whole grid, one line per row.
+  fn render_text<F>(&self, mut f: F) -> String
+  where
+    F: FnMut(Option<&T>) -> char,
+  {
+    match self.bounds() {
+      Some(bounds) => self
+        .render(&bounds, |_, value| f(value))
+        .iter()
+        .map(|row| row.iter().collect::<String>())
+        .collect::<Vec<String>>()
+        .join("\n"),
+      None => String::new(),
+    }
+  }
+}
+
+// Grid storing only the cells that were set, for unbounded worlds.
+#[derive(Clone, Default)]
+pub struct SparseGrid<T> {
+  cells: HashMap<Point, T>,
+  bounds: Option<Bounds>,
+}
+
+impl<T> SparseGrid<T> {
+  pub fn new() -> SparseGrid<T> {
+    SparseGrid {
+      cells: HashMap::new(),
+      bounds: None,
+    }
+  }
+
+  pub fn len(&self) -> usize {
+    self.cells.len()
+  }
+
+  pub fn is_empty(&self) -> bool {
+    self.cells.is_empty()
+  }
+
+  pub fn iter(&self) -> impl Iterator<Item = (&Point, &T)> {
+    self.cells.iter()
+  }
+}
+
+impl<T> Grid<T> for SparseGrid<T> {
+  fn get(&self, point: Point) -> Option<&T> {
+    self.cells.get(&point)
+  }
+
+  fn set(&mut self, point: Point, value: T) {
+    match &mut self.bounds {
+      Some(bounds) => bounds.extend(point),
+      None => self.bounds = Some(Bounds::from_point(point)),
+    }
+    self.cells.insert(point, value);
+  }
+
+  fn bounds(&self) -> Option<Bounds> {
+    self.bounds
+  }
+}
+
+/*
+ * Grid backed by a vector covering its whole bounds, for worlds known to be
+ * small and full. Setting a point outside of the bounds grows them, new cells
+ * taking the fill value.
+ */
+#[derive(Clone)]
+pub struct DenseGrid<T> {
+  cells: Vec<T>,
+  bounds: Bounds,
+  fill: T,
+}
+
+impl<T: Clone> DenseGrid<T> {
+  pub fn new(bounds: Bounds, fill: T) -> DenseGrid<T> {
+    DenseGrid {
+      cells: vec![fill.clone(); bounds.width() * bounds.height()],
+      bounds,
+      fill,
+    }
+  }
+
+  fn index(&self, point: Point) -> usize {
+    let (x, y) = self.bounds.offset(point);
+    y * self.bounds.width() + x
+  }
+
+  fn grow(&mut self, point: Point) {
+    let mut bounds = self.bounds;
+    bounds.extend(point);
+    let mut grown = DenseGrid::new(bounds, self.fill.clone());
+    for old in self.bounds.points() {
+      let index = grown.index(old);
+      grown.cells[index] = self.cells[self.index(old)].clone();
+    }
+    *self = grown;
+  }
+}
+
+impl<T: Clone> Grid<T> for DenseGrid<T> {
+  fn get(&self, point: Point) -> Option<&T> {
+    if self.bounds.contains(point) {
+      Some(&self.cells[self.index(point)])
+    } else {
+      None
+    }
+  }
+
+  fn set(&mut self, point: Point, value: T) {
+    if !self.bounds.contains(point) {
+      self.grow(point);
+    }
+    let index = self.index(point);
+    self.cells[index] = value;
+  }
+
+  fn bounds(&self) -> Option<Bounds> {
+    Some(self.bounds)
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn sparse_tracks_bounds() {
+    let mut grid = SparseGrid::new();
+    assert_eq!(grid.bounds(), None);
+    grid.set(Point::new(2, -1), 'a');
+    grid.set(Point::new(-1, 3), 'b');
+    assert_eq!(
+      grid.bounds(),
+      Some(Bounds {
+        top_left: Point::new(-1, -1),
+        bottom_right: Point::new(2, 3),
+      })
+    );
+    assert_eq!(grid.len(), 2);
+    assert_eq!(grid.get(Point::new(0, 0)), None);
+  }
+
+  #[test]
+  fn dense_grows_and_keeps_cells() {
+    let mut grid = DenseGrid::new(Bounds::from_point(Point::new(0, 0)), '.');
+    grid.set(Point::new(0, 0), '#');
+    grid.set(Point::new(-2, 1), '#');
+    assert_eq!(grid.render_text(|c| *c.unwrap()), "..#\n#..");
+    let neighbours: Vec<Point> = grid.neighbours(Point::new(-1, 0)).iter().map(|(p, _)| *p).collect();
+    assert_eq!(neighbours, vec![Point::new(0, 0), Point::new(-1, 1), Point::new(-2, 0)]);
+  }
+}
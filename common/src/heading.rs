@@ -0,0 +1,130 @@
+// Orientation and movement on a grid, y growing downwards.
+
+use crate::grid::Point;
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum Heading {
+  Up,
+  Right,
+  Down,
+  Left,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Turn {
+  Left,
+  Right,
+}
+
+impl Heading {
+  pub fn turned(self, turn: Turn) -> Heading {
+    match turn {
+      Turn::Left => self.left(),
+      Turn::Right => self.right(),
+    }
+  }
+
+  pub fn left(self) -> Heading {
+    match self {
+      Heading::Up => Heading::Left,
+      Heading::Right => Heading::Up,
+      Heading::Down => Heading::Right,
+      Heading::Left => Heading::Down,
+    }
+  }
+
+  pub fn right(self) -> Heading {
+    match self {
+      Heading::Up => Heading::Right,
+      Heading::Right => Heading::Down,
+      Heading::Down => Heading::Left,
+      Heading::Left => Heading::Up,
+    }
+  }
+
+  pub fn reversed(self) -> Heading {
+    self.right().right()
+  }
+
+  // Move of one step in this heading.
+  pub fn delta(self) -> Point {
+    match self {
+      Heading::Up => Point::new(0, -1),
+      Heading::Right => Point::new(1, 0),
+      Heading::Down => Point::new(0, 1),
+      Heading::Left => Point::new(-1, 0),
+    }
+  }
+
+  pub fn from_arrow(arrow: char) -> Option<Heading> {
+    match arrow {
+      '^' => Some(Heading::Up),
+      '>' => Some(Heading::Right),
+      'v' => Some(Heading::Down),
+      '<' => Some(Heading::Left),
+      _ => None,
+    }
+  }
+
+  pub fn arrow(self) -> char {
+    match self {
+      Heading::Up => '^',
+      Heading::Right => '>',
+      Heading::Down => 'v',
+      Heading::Left => '<',
+    }
+  }
+}
+
+pub fn get_heading(name: &str) -> Result<Heading, &'static str> {
+  match name {
+    "up" | "^" => Ok(Heading::Up),
+    "right" | ">" => Ok(Heading::Right),
+    "down" | "v" => Ok(Heading::Down),
+    "left" | "<" => Ok(Heading::Left),
+    _ => Err("Invalid heading, expected up, right, down or left"),
+  }
+}
+
+// Something standing on a grid that can only turn and move forward.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub struct Turtle {
+  pub position: Point,
+  pub heading: Heading,
+}
+
+impl Turtle {
+  pub fn new(position: Point, heading: Heading) -> Turtle {
+    Turtle { position, heading }
+  }
+
+  pub fn turn(&mut self, turn: Turn) {
+    self.heading = self.heading.turned(turn);
+  }
+
+  pub fn forward(&mut self) {
+    self.position = self.position + self.heading.delta();
+  }
+
+  // Cell the turtle would reach by moving forward.
+  pub fn ahead(&self) -> Point {
+    self.position + self.heading.delta()
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn turns_around() {
+    let mut turtle = Turtle::new(Point::new(0, 0), Heading::Up);
+    for turn in &[Turn::Right, Turn::Right, Turn::Left, Turn::Right, Turn::Right] {
+      turtle.turn(*turn);
+      turtle.forward();
+    }
+    assert_eq!(turtle.position, Point::new(1, 2));
+    assert_eq!(turtle.heading, Heading::Left);
+    assert_eq!(Heading::Up.reversed(), Heading::Down);
+  }
+}
@@ -0,0 +1,447 @@
+// Canonical Intcode programs, mostly taken from the puzzle statements of
+// days 2, 5 and 9, shared by every interpreter of the workspace so that
+// they cannot silently drift apart.
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Feature {
+  // Opcode 9 and parameter mode 2.
+  RelativeMode,
+  // Values that do not fit in an i32.
+  LargeNumbers,
+  // Reads and writes past the end of the program.
+  GrowingMemory,
+  // More than one input value, consumed in order.
+  MultipleInputs,
+  // The whole output sequence is checked, not only a single value.
+  OutputStream,
+  // Arithmetic overflows stop the program with an error instead of wrapping.
+  CheckedArithmetic,
+}
+
+pub enum Expected {
+  Outputs(&'static [i64]),
+  Memory(&'static [i64]),
+  Error,
+}
+
+pub struct Case {
+  pub name: &'static str,
+  pub program: &'static str,
+  pub inputs: &'static [i64],
+  pub expected: Expected,
+  pub features: &'static [Feature],
+}
+
+pub struct Execution {
+  pub outputs: Vec<i64>,
+  pub memory: Vec<i64>,
+}
+
+const LARGE_COMPARISON: &str = "3,21,1008,21,8,20,1005,20,22,107,8,21,20,1006,20,31,\
+  1106,0,36,98,0,0,1002,21,125,20,4,20,1105,1,46,104,999,1105,1,46,1101,1000,1,20,4,20,\
+  1105,1,46,98,99";
+
+const QUINE: &str = "109,1,204,-1,1001,100,1,100,1008,100,16,101,1006,101,0,99";
+
+pub const CASES: &[Case] = &[
+  Case {
+    name: "add position mode",
+    program: "1,0,0,0,99",
+    inputs: &[],
+    expected: Expected::Memory(&[2, 0, 0, 0, 99]),
+    features: &[],
+  },
+  Case {
+    name: "multiply position mode",
+    program: "2,3,0,3,99",
+    inputs: &[],
+    expected: Expected::Memory(&[2, 3, 0, 6, 99]),
+    features: &[],
+  },
+  Case {
+    name: "multiply after halt",
+    program: "2,4,4,5,99,0",
+    inputs: &[],
+    expected: Expected::Memory(&[2, 4, 4, 5, 99, 9801]),
+    features: &[],
+  },
+  Case {
+    name: "self-modifying halt",
+    program: "1,1,1,4,99,5,6,0,99",
+    inputs: &[],
+    expected: Expected::Memory(&[30, 1, 1, 4, 2, 5, 6, 0, 99]),
+    features: &[],
+  },
+  Case {
+    name: "add then multiply",
+    program: "1,9,10,3,2,3,11,0,99,30,40,50",
+    inputs: &[],
+    expected: Expected::Memory(&[3500, 9, 10, 70, 2, 3, 11, 0, 99, 30, 40, 50]),
+    features: &[],
+  },
+  Case {
+    name: "echo input",
+    program: "3,0,4,0,99",
+    inputs: &[42],
+    expected: Expected::Outputs(&[42]),
+    features: &[],
+  },
+  Case {
+    name: "multiply immediate mode",
+    program: "1002,4,3,4,33",
+    inputs: &[],
+    expected: Expected::Memory(&[1002, 4, 3, 4, 99]),
+    features: &[],
+  },
+  Case {
+    name: "add negative immediate",
+    program: "1101,100,-1,4,0",
+    inputs: &[],
+    expected: Expected::Memory(&[1101, 100, -1, 4, 99]),
+    features: &[],
+  },
+  Case {
+    name: "output immediate mode",
+    program: "104,7,99",
+    inputs: &[],
+    expected: Expected::Outputs(&[7]),
+    features: &[],
+  },
+  Case {
+    name: "equal to 8 position mode (equal)",
+    program: "3,9,8,9,10,9,4,9,99,-1,8",
+    inputs: &[8],
+    expected: Expected::Outputs(&[1]),
+    features: &[],
+  },
+  Case {
+    name: "equal to 8 position mode (different)",
+    program: "3,9,8,9,10,9,4,9,99,-1,8",
+    inputs: &[5],
+    expected: Expected::Outputs(&[0]),
+    features: &[],
+  },
+  Case {
+    name: "less than 8 position mode (less)",
+    program: "3,9,7,9,10,9,4,9,99,-1,8",
+    inputs: &[5],
+    expected: Expected::Outputs(&[1]),
+    features: &[],
+  },
+  Case {
+    name: "less than 8 position mode (equal)",
+    program: "3,9,7,9,10,9,4,9,99,-1,8",
+    inputs: &[8],
+    expected: Expected::Outputs(&[0]),
+    features: &[],
+  },
+  Case {
+    name: "equal to 8 immediate mode (equal)",
+    program: "3,3,1108,-1,8,3,4,3,99",
+    inputs: &[8],
+    expected: Expected::Outputs(&[1]),
+    features: &[],
+  },
+  Case {
+    name: "equal to 8 immediate mode (different)",
+    program: "3,3,1108,-1,8,3,4,3,99",
+    inputs: &[9],
+    expected: Expected::Outputs(&[0]),
+    features: &[],
+  },
+  Case {
+    name: "less than 8 immediate mode (less)",
+    program: "3,3,1107,-1,8,3,4,3,99",
+    inputs: &[7],
+    expected: Expected::Outputs(&[1]),
+    features: &[],
+  },
+  Case {
+    name: "less than 8 immediate mode (equal)",
+    program: "3,3,1107,-1,8,3,4,3,99",
+    inputs: &[8],
+    expected: Expected::Outputs(&[0]),
+    features: &[],
+  },
+  Case {
+    name: "jump position mode (zero)",
+    program: "3,12,6,12,15,1,13,14,13,4,13,99,-1,0,1,9",
+    inputs: &[0],
+    expected: Expected::Outputs(&[0]),
+    features: &[],
+  },
+  Case {
+    name: "jump position mode (non zero)",
+    program: "3,12,6,12,15,1,13,14,13,4,13,99,-1,0,1,9",
+    inputs: &[3],
+    expected: Expected::Outputs(&[1]),
+    features: &[],
+  },
+  Case {
+    name: "jump immediate mode (zero)",
+    program: "3,3,1105,-1,9,1101,0,0,12,4,12,99,1",
+    inputs: &[0],
+    expected: Expected::Outputs(&[0]),
+    features: &[],
+  },
+  Case {
+    name: "jump immediate mode (non zero)",
+    program: "3,3,1105,-1,9,1101,0,0,12,4,12,99,1",
+    inputs: &[3],
+    expected: Expected::Outputs(&[1]),
+    features: &[],
+  },
+  Case {
+    name: "compare to 8 (below)",
+    program: LARGE_COMPARISON,
+    inputs: &[7],
+    expected: Expected::Outputs(&[999]),
+    features: &[],
+  },
+  Case {
+    name: "compare to 8 (equal)",
+    program: LARGE_COMPARISON,
+    inputs: &[8],
+    expected: Expected::Outputs(&[1000]),
+    features: &[],
+  },
+  Case {
+    name: "compare to 8 (above)",
+    program: LARGE_COMPARISON,
+    inputs: &[9],
+    expected: Expected::Outputs(&[1001]),
+    features: &[],
+  },
+  Case {
+    name: "add two inputs",
+    program: "3,11,3,12,1,11,12,13,4,13,99,0,0,0",
+    inputs: &[20, 22],
+    expected: Expected::Outputs(&[42]),
+    features: &[Feature::MultipleInputs],
+  },
+  Case {
+    name: "halt without output",
+    program: "99",
+    inputs: &[],
+    expected: Expected::Outputs(&[]),
+    features: &[Feature::OutputStream],
+  },
+  Case {
+    name: "several outputs",
+    program: "104,1,104,2,104,3,99",
+    inputs: &[],
+    expected: Expected::Outputs(&[1, 2, 3]),
+    features: &[Feature::OutputStream],
+  },
+  Case {
+    name: "invalid opcode",
+    program: "42,0,0,0,99",
+    inputs: &[],
+    expected: Expected::Error,
+    features: &[],
+  },
+  Case {
+    name: "write in immediate mode",
+    program: "11101,1,1,5,99,0",
+    inputs: &[],
+    expected: Expected::Error,
+    features: &[],
+  },
+  Case {
+    name: "input in immediate mode",
+    program: "103,0,99",
+    inputs: &[1],
+    expected: Expected::Error,
+    features: &[],
+  },
+  Case {
+    name: "invalid mode digit",
+    program: "1301,0,0,0,99",
+    inputs: &[],
+    expected: Expected::Error,
+    features: &[],
+  },
+  Case {
+    name: "read past the end",
+    program: "4,100,99",
+    inputs: &[],
+    expected: Expected::Outputs(&[0]),
+    features: &[Feature::GrowingMemory],
+  },
+  Case {
+    name: "write past the end",
+    program: "1101,20,22,100,4,100,99",
+    inputs: &[],
+    expected: Expected::Outputs(&[42]),
+    features: &[Feature::GrowingMemory],
+  },
+  Case {
+    name: "relative mode input and output",
+    program: "109,10,203,0,204,0,99",
+    inputs: &[42],
+    expected: Expected::Outputs(&[42]),
+    features: &[Feature::RelativeMode, Feature::GrowingMemory],
+  },
+  Case {
+    name: "relative mode add",
+    program: "109,7,22201,0,1,2,204,2,99",
+    inputs: &[],
+    expected: Expected::Outputs(&[101]),
+    features: &[Feature::RelativeMode, Feature::GrowingMemory],
+  },
+  Case {
+    name: "relative mode multiply",
+    program: "109,7,22202,0,1,2,204,2,99",
+    inputs: &[],
+    expected: Expected::Outputs(&[198]),
+    features: &[Feature::RelativeMode, Feature::GrowingMemory],
+  },
+  Case {
+    name: "jump if true relative mode",
+    program: "109,9,2205,1,0,104,0,99,0,10,104,1,99",
+    inputs: &[],
+    expected: Expected::Outputs(&[1]),
+    features: &[Feature::RelativeMode],
+  },
+  Case {
+    name: "jump if false relative mode",
+    program: "109,9,2206,-1,0,104,0,99,0,10,104,1,99",
+    inputs: &[],
+    expected: Expected::Outputs(&[1]),
+    features: &[Feature::RelativeMode],
+  },
+  Case {
+    name: "less than relative mode (less)",
+    program: "109,20,203,0,203,1,22207,0,1,2,204,2,99",
+    inputs: &[3, 5],
+    expected: Expected::Outputs(&[1]),
+    features: &[Feature::RelativeMode, Feature::GrowingMemory, Feature::MultipleInputs],
+  },
+  Case {
+    name: "less than relative mode (greater)",
+    program: "109,20,203,0,203,1,22207,0,1,2,204,2,99",
+    inputs: &[5, 3],
+    expected: Expected::Outputs(&[0]),
+    features: &[Feature::RelativeMode, Feature::GrowingMemory, Feature::MultipleInputs],
+  },
+  Case {
+    name: "equals relative mode (equal)",
+    program: "109,20,203,0,203,1,22208,0,1,2,204,2,99",
+    inputs: &[4, 4],
+    expected: Expected::Outputs(&[1]),
+    features: &[Feature::RelativeMode, Feature::GrowingMemory, Feature::MultipleInputs],
+  },
+  Case {
+    name: "equals relative mode (different)",
+    program: "109,20,203,0,203,1,22208,0,1,2,204,2,99",
+    inputs: &[4, 5],
+    expected: Expected::Outputs(&[0]),
+    features: &[Feature::RelativeMode, Feature::GrowingMemory, Feature::MultipleInputs],
+  },
+  Case {
+    name: "write in relative mode with a negative base",
+    program: "109,-1,21101,1,1,0,99",
+    inputs: &[],
+    expected: Expected::Error,
+    features: &[Feature::RelativeMode],
+  },
+  Case {
+    name: "quine",
+    program: QUINE,
+    inputs: &[],
+    expected: Expected::Outputs(&[
+      109, 1, 204, -1, 1001, 100, 1, 100, 1008, 100, 16, 101, 1006, 101, 0, 99,
+    ]),
+    features: &[
+      Feature::RelativeMode,
+      Feature::GrowingMemory,
+      Feature::OutputStream,
+    ],
+  },
+  Case {
+    name: "sixteen digit product",
+    program: "1102,34915192,34915192,7,4,7,99,0",
+    inputs: &[],
+    expected: Expected::Outputs(&[1219070632396864]),
+    features: &[Feature::LargeNumbers],
+  },
+  Case {
+    name: "large immediate output",
+    program: "104,1125899906842624,99",
+    inputs: &[],
+    expected: Expected::Outputs(&[1125899906842624]),
+    features: &[Feature::LargeNumbers],
+  },
+  Case {
+    name: "add overflow",
+    program: "1101,9223372036854775807,1,0,99",
+    inputs: &[],
+    expected: Expected::Error,
+    features: &[Feature::LargeNumbers, Feature::CheckedArithmetic],
+  },
+  Case {
+    name: "multiply overflow",
+    program: "1102,4611686018427387904,2,0,99",
+    inputs: &[],
+    expected: Expected::Error,
+    features: &[Feature::LargeNumbers, Feature::CheckedArithmetic],
+  },
+];
+
+pub fn parse_program(program: &str) -> Vec<i64> {
+  program
+    .split(',')
+    .map(|n| n.trim().parse::<i64>().expect("Could not parse number"))
+    .collect()
+}
+
+fn check_case(case: &Case, result: Result<Execution, &'static str>) -> Result<(), String> {
+  match (&case.expected, result) {
+    (Expected::Error, Err(_)) => Ok(()),
+    (Expected::Error, Ok(execution)) => Err(format!(
+      "expected an error, got outputs {:?}",
+      execution.outputs
+    )),
+    (_, Err(e)) => Err(format!("unexpected error: {}", e)),
+    (Expected::Outputs(outputs), Ok(execution)) => {
+      if execution.outputs == *outputs {
+        Ok(())
+      } else {
+        Err(format!(
+          "expected outputs {:?}, got {:?}",
+          outputs, execution.outputs
+        ))
+      }
+    }
+    (Expected::Memory(memory), Ok(execution)) => {
+      if execution.memory.starts_with(memory) {
+        Ok(())
+      } else {
+        Err(format!(
+          "expected memory {:?}, got {:?}",
+          memory, execution.memory
+        ))
+      }
+    }
+  }
+}
+
+/*
+ * Runs every case whose features are all supported and returns a description
+ * of each one that failed. `run` receives the parsed program and its inputs.
+ */
+pub fn run_suite<F>(supported: &[Feature], run: F) -> Vec<String>
+where
+  F: Fn(&[i64], &[i64]) -> Result<Execution, &'static str>,
+{
+  CASES
+    .iter()
+    .filter(|case| case.features.iter().all(|f| supported.contains(f)))
+    .filter_map(|case| {
+      let program = parse_program(case.program);
+      check_case(case, run(&program, case.inputs))
+        .err()
+        .map(|e| format!("{}: {}", case.name, e))
+    })
+    .collect()
+}
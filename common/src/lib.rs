@@ -0,0 +1,8 @@
+pub mod bench;
+pub mod grid;
+pub mod heading;
+pub mod intcode_conformance;
+pub mod ocr;
+pub mod rng;
+pub mod svg;
+pub mod vm;
@@ -0,0 +1,197 @@
+// Recognition of the 6 rows high block letters drawn by several puzzles. Most
+// letters are 4 columns wide and followed by a blank column, but I is only 3
+// columns wide and Y fills 5. Glyphs are trimmed to their lit columns, so a
+// letter starts at the first lit column after the previous one.
+
+pub const GLYPH_WIDTH: usize = 4;
+pub const GLYPH_HEIGHT: usize = 6;
+
+const ALPHABET: &[(char, [&str; GLYPH_HEIGHT])] = &[
+  ('A', [".##.", "#..#", "#..#", "####", "#..#", "#..#"]),
+  ('B', ["###.", "#..#", "###.", "#..#", "#..#", "###."]),
+  ('C', [".##.", "#..#", "#...", "#...", "#..#", ".##."]),
+  ('E', ["####", "#...", "###.", "#...", "#...", "####"]),
+  ('F', ["####", "#...", "###.", "#...", "#...", "#..."]),
+  ('G', [".##.", "#..#", "#...", "#.##", "#..#", ".###"]),
+  ('H', ["#..#", "#..#", "####", "#..#", "#..#", "#..#"]),
+  ('I', ["###", ".#.", ".#.", ".#.", ".#.", "###"]),
+  ('J', ["..##", "...#", "...#", "...#", "#..#", ".##."]),
+  ('K', ["#..#", "#.#.", "##..", "#.#.", "#.#.", "#..#"]),
+  ('L', ["#...", "#...", "#...", "#...", "#...", "####"]),
+  ('O', [".##.", "#..#", "#..#", "#..#", "#..#", ".##."]),
+  ('P', ["###.", "#..#", "#..#", "###.", "#...", "#..."]),
+  ('R', ["###.", "#..#", "#..#", "###.", "#.#.", "#..#"]),
+  ('S', [".###", "#...", "#...", ".##.", "...#", "###."]),
+  ('U', ["#..#", "#..#", "#..#", "#..#", "#..#", ".##."]),
+  ('Y', ["#...#", "#...#", ".#.#.", "..#..", "..#..", "..#.."]),
+  ('Z', ["####", "...#", "..#.", ".#..", "#...", "####"]),
+];
+
+#[derive(Debug, PartialEq)]
+pub enum OcrError {
+  // Once blank rows are trimmed, the letters are not 6 rows high.
+  InvalidHeight(usize),
+  // Columns where a glyph starts that matches no letter, and the text read
+  // with `?` in their place.
+  UnknownGlyphs { columns: Vec<usize>, text: String },
+}
+
+impl std::fmt::Display for OcrError {
+  fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+    match self {
+      OcrError::InvalidHeight(height) => {
+        write!(f, "letters must be {} rows high, found {}", GLYPH_HEIGHT, height)
+      }
+      OcrError::UnknownGlyphs { columns, text } => {
+        let columns: Vec<String> = columns.iter().map(|c| c.to_string()).collect();
+        write!(f, "unknown glyphs at columns {} in {}", columns.join(", "), text)
+      }
+    }
+  }
+}
+
+// Letter starting at `column`, with its width.
+fn get_glyph(rows: &[&Vec<bool>], column: usize) -> Option<(char, usize)> {
+  ALPHABET
+    .iter()
+    .find(|(_, glyph)| {
+      glyph.iter().zip(rows).all(|(pattern, row)| {
+        pattern
+          .chars()
+          .enumerate()
+          .all(|(i, c)| (c == '#') == row.get(column + i).cloned().unwrap_or(false))
+      })
+    })
+    .map(|(letter, glyph)| (*letter, glyph[0].len()))
+}
+
+/*
+ * Reads the letters of a grid of lit pixels, given row by row. Rows do not need
+ * to have the same length, missing pixels are off.
+ */
+pub fn recognize(grid: &[Vec<bool>]) -> Result<String, OcrError> {
+  let rows: Vec<&Vec<bool>> = grid
+    .iter()
+    .skip_while(|row| !row.contains(&true))
+    .collect();
+  let height = rows.iter().rposition(|row| row.contains(&true)).map_or(0, |last| last + 1);
+  if height != GLYPH_HEIGHT {
+    return Err(OcrError::InvalidHeight(height));
+  }
+  let rows = &rows[..height];
+  let width = rows.iter().map(|row| row.len()).max().unwrap_or(0);
+  let is_blank = |column: usize| rows.iter().all(|row| !row.get(column).cloned().unwrap_or(false));
+  let mut text = String::new();
+  let mut unknown = Vec::new();
+  let mut column = 0;
+  while column < width {
+    if is_blank(column) {
+      column += 1;
+      continue;
+    }
+    match get_glyph(rows, column) {
+      Some((letter, glyph_width)) => {
+        text.push(letter);
+        column += glyph_width;
+      }
+      None => {
+        text.push('?');
+        unknown.push(column);
+        column += GLYPH_WIDTH;
+      }
+    }
+  }
+  if unknown.is_empty() {
+    Ok(text)
+  } else {
+    Err(OcrError::UnknownGlyphs {
+      columns: unknown,
+      text,
+    })
+  }
+}
+
+// Convenience for grids already drawn as text, `lit` marking the lit pixels.
+pub fn recognize_text(text: &str, lit: char) -> Result<String, OcrError> {
+  let grid: Vec<Vec<bool>> = text
+    .lines()
+    .map(|line| line.chars().map(|c| c == lit).collect())
+    .collect();
+  recognize(&grid)
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn reads_every_letter() {
+    let mut text = vec![String::new(); GLYPH_HEIGHT];
+    for (_, glyph) in ALPHABET {
+      for (line, row) in text.iter_mut().zip(glyph.iter()) {
+        line.push_str(row);
+        line.push('.');
+      }
+    }
+    let expected: String = ALPHABET.iter().map(|(letter, _)| *letter).collect();
+    assert_eq!(recognize_text(&text.join("\n"), '#'), Ok(expected));
+  }
+
+  #[test]
+  fn reads_narrow_and_wide_letters() {
+    // I is centered in a 5 columns cell, Y fills its cell up to the next letter.
+    let text = "\
+      .###.#...####.\n\
+      ..#..#...##..#\n\
+      ..#...#.#.###.\n\
+      ..#....#..#..#\n\
+      ..#....#..#..#\n\
+      .###...#..###.";
+    assert_eq!(recognize_text(text, '#'), Ok(String::from("IYB")));
+    let text = "\
+      ###..###.\n\
+      #..#..#..\n\
+      ###...#..\n\
+      #..#..#..\n\
+      #..#..#..\n\
+      ###..###.";
+    assert_eq!(recognize_text(text, '#'), Ok(String::from("BI")));
+  }
+
+  #[test]
+  fn ignores_margins() {
+    let text = "\n\
+      ..........\n\
+      ..###.....\n\
+      ..#..#....\n\
+      ..###.....\n\
+      ..#..#....\n\
+      ..#..#....\n\
+      ..###.....\n\
+      ..........";
+    assert_eq!(recognize_text(text, '#'), Ok(String::from("B")));
+  }
+
+  #[test]
+  fn reports_unknown_glyphs() {
+    let text = "\
+      ####.####\n\
+      ####.#...\n\
+      ####.###.\n\
+      ####.#...\n\
+      ####.#...\n\
+      ####.####";
+    assert_eq!(
+      recognize_text(text, '#'),
+      Err(OcrError::UnknownGlyphs {
+        columns: vec![0],
+        text: String::from("?E"),
+      })
+    );
+  }
+
+  #[test]
+  fn rejects_wrong_height() {
+    assert_eq!(recognize_text("####\n#..#", '#'), Err(OcrError::InvalidHeight(2)));
+  }
+}
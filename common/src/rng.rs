@@ -0,0 +1,85 @@
+// xorshift64*, reproducible from its seed and good enough for benchmarks and
+// generated test cases. Not meant for anything that must be unpredictable.
+
+use std::convert::TryFrom;
+
+pub struct Rng {
+  state: u64,
+}
+
+impl Rng {
+  // A zero state would only ever produce zeros, so seed 0 behaves like seed 1.
+  pub fn new(seed: u64) -> Rng {
+    Rng { state: seed.max(1) }
+  }
+
+  pub fn next_u64(&mut self) -> u64 {
+    self.state ^= self.state >> 12;
+    self.state ^= self.state << 25;
+    self.state ^= self.state >> 27;
+    self.state.wrapping_mul(0x2545_f491_4f6c_dd1d)
+  }
+
+  // A number in 0..n, n being at least 1.
+  pub fn below(&mut self, n: u64) -> u64 {
+    assert!(n > 0, "Empty range");
+    self.next_u64() % n
+  }
+
+  /*
+   * A number in min..=max, for any integer type up to 64 bits. The span is
+   * computed on 128 bits so that the whole range of a type does not overflow.
+   */
+  pub fn range<T>(&mut self, min: T, max: T) -> T
+  where
+    T: TryFrom<i128>,
+    i128: TryFrom<T>,
+  {
+    let low = i128::try_from(min).ok().expect("Integer wider than 64 bits");
+    let high = i128::try_from(max).ok().expect("Integer wider than 64 bits");
+    assert!(low <= high, "Empty range");
+    let span = high - low + 1;
+    let offset = if span > u64::MAX as i128 {
+      self.next_u64() as i128
+    } else {
+      self.below(span as u64) as i128
+    };
+    T::try_from(low + offset).ok().unwrap()
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn stays_in_range() {
+    let mut rng = Rng::new(7);
+    for _ in 0..1000 {
+      let value = rng.range(-3i32, 3);
+      assert!((-3..=3).contains(&value));
+      assert!(rng.below(5) < 5);
+    }
+    assert_eq!(rng.range(4usize, 4), 4);
+  }
+
+  #[test]
+  fn covers_whole_types() {
+    let mut rng = Rng::new(1);
+    let values: Vec<usize> = (0..100).map(|_| rng.range(0, usize::MAX)).collect();
+    assert!(values.iter().any(|value| *value > usize::MAX / 2));
+    rng.range(i64::MIN, i64::MAX);
+    rng.range(u8::MIN, u8::MAX);
+  }
+
+  #[test]
+  fn repeats_from_a_seed() {
+    let draw = |seed| {
+      let mut rng = Rng::new(seed);
+      (0..10).map(|_| rng.next_u64()).collect::<Vec<u64>>()
+    };
+    assert_eq!(draw(42), draw(42));
+    assert_ne!(draw(42), draw(43));
+    assert_eq!(draw(0), draw(1));
+  }
+}
@@ -0,0 +1,147 @@
+// Minimal SVG documents: shapes are added in drawing order and the view box
+// grows to hold all of them.
+
+use crate::grid::{Bounds, Grid};
+use std::fmt::Write;
+
+pub struct Svg {
+  elements: Vec<String>,
+  min: (f64, f64),
+  max: (f64, f64),
+}
+
+impl Default for Svg {
+  fn default() -> Svg {
+    Svg::new()
+  }
+}
+
+impl Svg {
+  pub fn new() -> Svg {
+    Svg {
+      elements: Vec::new(),
+      min: (f64::INFINITY, f64::INFINITY),
+      max: (f64::NEG_INFINITY, f64::NEG_INFINITY),
+    }
+  }
+
+  fn include(&mut self, x: f64, y: f64) {
+    self.min = (self.min.0.min(x), self.min.1.min(y));
+    self.max = (self.max.0.max(x), self.max.1.max(y));
+  }
+
+  // Largest side of the drawing so far, to size markers relative to it.
+  pub fn get_span(&self) -> f64 {
+    if self.elements.is_empty() {
+      return 0.0;
+    }
+    (self.max.0 - self.min.0).max(self.max.1 - self.min.1)
+  }
+
+  pub fn add_rect(&mut self, x: f64, y: f64, width: f64, height: f64, fill: &str) {
+    self.include(x, y);
+    self.include(x + width, y + height);
+    self.elements.push(format!(
+      "<rect x=\"{}\" y=\"{}\" width=\"{}\" height=\"{}\" fill=\"{}\"/>",
+      x, y, width, height, fill
+    ));
+  }
+
+  // Strokes keep their width in pixels however far the drawing is zoomed.
+  pub fn add_polyline(&mut self, points: &[(f64, f64)], stroke: &str, width: f64) {
+    let mut coordinates = String::new();
+    for &(x, y) in points {
+      self.include(x, y);
+      write!(coordinates, "{},{} ", x, y).unwrap();
+    }
+    self.elements.push(format!(
+      "<polyline points=\"{}\" fill=\"none\" stroke=\"{}\" stroke-width=\"{}\" \
+       stroke-linejoin=\"round\" vector-effect=\"non-scaling-stroke\"/>",
+      coordinates.trim_end(),
+      stroke,
+      width
+    ));
+  }
+
+  pub fn add_circle(&mut self, x: f64, y: f64, radius: f64, fill: &str) {
+    self.include(x - radius, y - radius);
+    self.include(x + radius, y + radius);
+    self.elements.push(format!(
+      "<circle cx=\"{}\" cy=\"{}\" r=\"{}\" fill=\"{}\"/>",
+      x, y, radius, fill
+    ));
+  }
+
+  // One unit square per cell of `bounds` that `fill` gives a color to.
+  pub fn add_cells<T, G, F>(&mut self, grid: &G, bounds: &Bounds, mut fill: F)
+  where
+    G: Grid<T>,
+    F: FnMut(Option<&T>) -> Option<String>,
+  {
+    for point in bounds.points() {
+      if let Some(color) = fill(grid.get(point)) {
+        self.add_rect(point.x as f64, point.y as f64, 1.0, 1.0, &color);
+      }
+    }
+  }
+
+  /*
+   * The document is `width` pixels wide, its height following the aspect of
+   * the drawing, with a small margin around it.
+   */
+  pub fn to_document(&self, width: u32) -> String {
+    let (min, max) = if self.elements.is_empty() {
+      ((0.0, 0.0), (1.0, 1.0))
+    } else {
+      (self.min, self.max)
+    };
+    let margin = ((max.0 - min.0).max(max.1 - min.1) * 0.02).max(0.5);
+    let view_width = max.0 - min.0 + 2.0 * margin;
+    let view_height = max.1 - min.1 + 2.0 * margin;
+    let height = (width as f64 * view_height / view_width).ceil() as u32;
+    let mut document = format!(
+      "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{}\" height=\"{}\" viewBox=\"{} {} {} {}\">\n",
+      width,
+      height,
+      min.0 - margin,
+      min.1 - margin,
+      view_width,
+      view_height
+    );
+    for element in &self.elements {
+      document += "  ";
+      document += element;
+      document += "\n";
+    }
+    document += "</svg>\n";
+    document
+  }
+}
+
+pub fn write_svg(svg: &Svg, width: u32, filename: &str) -> Result<(), String> {
+  std::fs::write(filename, svg.to_document(width)).map_err(|e| format!("Could not write {}: {}", filename, e))
+}
+
+// `#rrggbb` notation of a color.
+pub fn get_color(color: [u8; 3]) -> String {
+  format!("#{:02x}{:02x}{:02x}", color[0], color[1], color[2])
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn frames_every_shape() {
+    let mut svg = Svg::new();
+    svg.add_rect(0.0, 0.0, 10.0, 10.0, "white");
+    svg.add_polyline(&[(0.0, 0.0), (20.0, 0.0)], "red", 2.0);
+    assert_eq!(svg.get_span(), 20.0);
+    let document = svg.to_document(100);
+    assert!(document.starts_with(
+      "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"100\" height=\"53\" viewBox=\"-0.5 -0.5 21 11\">"
+    ));
+    assert!(document.contains("<polyline points=\"0,0 20,0\""));
+    assert_eq!(get_color([255, 0, 16]), "#ff0010");
+  }
+}
@@ -0,0 +1,592 @@
+pub type BigByte = i64;
+
+pub type Memory = std::collections::HashMap<BigByte, BigByte>;
+
+pub fn parse_memory_file(filename: impl AsRef<std::path::Path>) -> Memory {
+	use std::io::prelude::*;
+	let mut file = std::fs::File::open(filename).expect("File not found");
+	let mut contents = String::new();
+	file.read_to_string(&mut contents)
+		.expect("Could not read file");
+	parse_memory(&contents)
+}
+
+pub fn parse_memory(program: &str) -> Memory {
+	let mut memory = Memory::new();
+	for (index, string_number) in (0..).zip(program.trim().split(',')) {
+		let number = string_number
+			.parse::<BigByte>()
+			.expect("Could not parse number");
+		memory.insert(index, number);
+	}
+	memory
+}
+
+pub type Input = std::collections::LinkedList<BigByte>;
+pub type Output = std::collections::LinkedList<BigByte>;
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum Mode {
+	Position = 0,
+	Immediate = 1,
+	Relative = 2,
+}
+
+pub type Parameters = [Mode; 3];
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum Tier {
+	// Decodes the instruction under the pointer at every step.
+	Interpreter,
+	// Keeps decoded instructions until their opcode cell is written to.
+	Cached,
+}
+
+// Everything `get_fingerprint` hashes, to tell a repeated state from a collision.
+#[derive(PartialEq)]
+pub struct Snapshot {
+	// Nonzero cells sorted by address.
+	memory: Vec<(BigByte, BigByte)>,
+	pointer: BigByte,
+	relative_base: BigByte,
+	inputs: Vec<BigByte>,
+	outputs: Vec<BigByte>,
+}
+
+pub struct VirtualMachine {
+	memory: Memory,
+	pointer: BigByte,
+	inputs: Input,
+	outputs: Output,
+	relative_base: BigByte,
+	tier: Tier,
+	decoded: std::collections::HashMap<BigByte, (BigByte, Parameters)>,
+	// Sum of the hashes of the memory cells, kept up to date by `set_byte`.
+	memory_fingerprint: u64,
+}
+
+// Zero for cells holding zero, which behave like cells never written.
+fn get_cell_hash(address: BigByte, value: BigByte) -> u64 {
+	use std::hash::{Hash, Hasher};
+	if value == 0 {
+		return 0;
+	}
+	let mut hasher = std::collections::hash_map::DefaultHasher::new();
+	(address, value).hash(&mut hasher);
+	hasher.finish()
+}
+
+pub fn get_tier(name: &str) -> Result<Tier, &'static str> {
+	match name {
+		"interpreter" => Ok(Tier::Interpreter),
+		"cached" => Ok(Tier::Cached),
+		_ => Err("Invalid tier, expected interpreter or cached"),
+	}
+}
+
+fn get_mode(value: BigByte) -> Result<Mode, &'static str> {
+	match value {
+		0 => Ok(Mode::Position),
+		1 => Ok(Mode::Immediate),
+		2 => Ok(Mode::Relative),
+		_ => Err("Invalid value"),
+	}
+}
+
+pub fn get_method(id: BigByte) -> Result<(BigByte, Parameters), &'static str> {
+	Ok((
+		id % 100,
+		[
+			get_mode(id / 100 % 10)?,
+			get_mode(id / 1000 % 10)?,
+			get_mode(id / 10000 % 10)?,
+		],
+	))
+}
+
+impl VirtualMachine {
+	fn get_byte(&self, pointer: BigByte) -> BigByte {
+		if self.memory.contains_key(&pointer) {
+			self.memory[&pointer]
+		} else {
+			0
+		}
+	}
+
+	fn set_byte(&mut self, pointer: BigByte, value: BigByte) {
+		let old = self.memory.insert(pointer, value).unwrap_or(0);
+		self.memory_fingerprint = self
+			.memory_fingerprint
+			.wrapping_sub(get_cell_hash(pointer, old))
+			.wrapping_add(get_cell_hash(pointer, value));
+		self.decoded.remove(&pointer);
+	}
+
+	fn get_value(&self, parameters: &Parameters, arg_id: BigByte) -> Result<BigByte, &'static str> {
+		let arg = self.get_byte(self.pointer + arg_id);
+		match parameters[arg_id as usize - 1] {
+			Mode::Position => {
+				if arg < 0 {
+					return Err("Negative parameter in position mode");
+				}
+				Ok(self.get_byte(arg))
+			}
+			Mode::Immediate => Ok(arg),
+			Mode::Relative => {
+				let pointer = self.relative_base + arg;
+				if pointer < 0 {
+					return Err("Invalid relative pointer < 0");
+				}
+				Ok(self.get_byte(pointer))
+			}
+		}
+	}
+
+	fn get_result_pointer(
+		&self,
+		parameters: &Parameters,
+		arg_id: BigByte,
+	) -> Result<BigByte, &'static str> {
+		let arg = self.get_byte(self.pointer + arg_id);
+		match parameters[arg_id as usize - 1] {
+			Mode::Position => {
+				if arg < 0 {
+					return Err("Negative parameter in position mode");
+				}
+				Ok(arg)
+			}
+			Mode::Immediate => Err("Result cannot have immediate parameter"),
+			Mode::Relative => {
+				let pointer = self.relative_base + arg;
+				if pointer < 0 {
+					Err("Invalid relative pointer < 0")
+				} else {
+					Ok(pointer)
+				}
+			}
+		}
+	}
+
+	fn set_result(
+		&mut self,
+		parameters: &Parameters,
+		arg_id: BigByte,
+		result: BigByte,
+	) -> Result<(), &'static str> {
+		let ptr = self.get_result_pointer(parameters, arg_id)?;
+		self.set_byte(ptr, result);
+		Ok(())
+	}
+
+	fn add(&mut self, parameters: &Parameters) -> Result<(), &'static str> {
+		let value_left = self.get_value(parameters, 1)?;
+		let value_right = self.get_value(parameters, 2)?;
+		let result = value_left
+			.checked_add(value_right)
+			.ok_or("Arithmetic overflow")?;
+		self.set_result(parameters, 3, result)?;
+		self.pointer += 4;
+		Ok(())
+	}
+
+	fn mult(&mut self, parameters: &Parameters) -> Result<(), &'static str> {
+		let value_left = self.get_value(parameters, 1)?;
+		let value_right = self.get_value(parameters, 2)?;
+		let result = value_left
+			.checked_mul(value_right)
+			.ok_or("Arithmetic overflow")?;
+		self.set_result(parameters, 3, result)?;
+		self.pointer += 4;
+		Ok(())
+	}
+
+	fn store_input(&mut self, parameters: &Parameters) -> Result<(), &'static str> {
+		match self.inputs.pop_front() {
+			Some(input) => {
+				// so Rust do not believe I am sharing it with a mutable reference of self
+				self.set_result(parameters, 1, input)?;
+				self.pointer += 2;
+				Ok(())
+			}
+			None => Err("No input found"),
+		}
+	}
+
+	fn store_output(&mut self, parameters: &Parameters) -> Result<(), &'static str> {
+		let output = self.get_value(parameters, 1)?;
+		self.outputs.push_back(output);
+		self.pointer += 2;
+		Ok(())
+	}
+
+	fn jump_if_true(&mut self, parameters: &Parameters) -> Result<(), &'static str> {
+		let value_left = self.get_value(parameters, 1)?;
+		let value_right = self.get_value(parameters, 2)?;
+		if value_left != 0 {
+			self.pointer = value_right;
+		} else {
+			self.pointer += 3;
+		}
+		Ok(())
+	}
+
+	fn jump_if_false(&mut self, parameters: &Parameters) -> Result<(), &'static str> {
+		let value_left = self.get_value(parameters, 1)?;
+		let value_right = self.get_value(parameters, 2)?;
+		if value_left == 0 {
+			self.pointer = value_right;
+		} else {
+			self.pointer += 3;
+		}
+		Ok(())
+	}
+
+	fn less_than(&mut self, parameters: &Parameters) -> Result<(), &'static str> {
+		let value_left = self.get_value(parameters, 1)?;
+		let value_right = self.get_value(parameters, 2)?;
+		self.set_result(parameters, 3, (value_left < value_right) as BigByte)?;
+		self.pointer += 4;
+		Ok(())
+	}
+
+	fn equals(&mut self, parameters: &Parameters) -> Result<(), &'static str> {
+		let value_left = self.get_value(parameters, 1)?;
+		let value_right = self.get_value(parameters, 2)?;
+		self.set_result(parameters, 3, (value_left == value_right) as BigByte)?;
+		self.pointer += 4;
+		Ok(())
+	}
+
+	fn update_relative_base(&mut self, parameters: &Parameters) -> Result<(), &'static str> {
+		self.relative_base += self.get_value(parameters, 1)?;
+		self.pointer += 2;
+		Ok(())
+	}
+
+	fn get_current_method(&self) -> Result<(BigByte, Parameters), &'static str> {
+		get_method(self.get_byte(self.pointer))
+	}
+
+	fn get_cached_method(&mut self) -> Result<(BigByte, Parameters), &'static str> {
+		if let Some(method) = self.decoded.get(&self.pointer) {
+			return Ok(*method);
+		}
+		let method = self.get_current_method()?;
+		self.decoded.insert(self.pointer, method);
+		Ok(method)
+	}
+
+	pub fn run(&mut self) -> Result<(), &'static str> {
+		loop {
+			let (op_code, parameters) = match self.tier {
+				Tier::Interpreter => self.get_current_method()?,
+				Tier::Cached => self.get_cached_method()?,
+			};
+			match op_code {
+				1 => self.add(&parameters)?,
+				2 => self.mult(&parameters)?,
+				3 => {
+					// Pauses until an input comes, other errors stop the program.
+					if self.inputs.is_empty() {
+						return Ok(());
+					}
+					self.store_input(&parameters)?
+				}
+				4 => self.store_output(&parameters)?,
+				5 => self.jump_if_true(&parameters)?,
+				6 => self.jump_if_false(&parameters)?,
+				7 => self.less_than(&parameters)?,
+				8 => self.equals(&parameters)?,
+				9 => self.update_relative_base(&parameters)?,
+				99 => {
+					return Ok(());
+				}
+				_ => return Err("Invalid instruction"),
+			}
+		}
+	}
+
+	pub fn pop_output(&mut self) -> Option<BigByte> {
+		self.outputs.pop_front()
+	}
+
+	pub fn is_running(&self) -> bool {
+		self.get_byte(self.pointer) != 99
+	}
+
+	pub fn push_input(&mut self, input: BigByte) {
+		self.inputs.push_back(input);
+	}
+
+	// Hash of everything that decides what the program does next.
+	pub fn get_fingerprint(&self) -> u64 {
+		use std::hash::{Hash, Hasher};
+		let mut hasher = std::collections::hash_map::DefaultHasher::new();
+		(
+			self.memory_fingerprint,
+			self.pointer,
+			self.relative_base,
+			&self.inputs,
+			&self.outputs,
+		)
+			.hash(&mut hasher);
+		hasher.finish()
+	}
+
+	pub fn get_snapshot(&self) -> Snapshot {
+		let mut memory: Vec<(BigByte, BigByte)> = self
+			.memory
+			.iter()
+			.filter(|(_, value)| **value != 0)
+			.map(|(address, value)| (*address, *value))
+			.collect();
+		memory.sort_unstable();
+		Snapshot {
+			memory,
+			pointer: self.pointer,
+			relative_base: self.relative_base,
+			inputs: self.inputs.iter().cloned().collect(),
+			outputs: self.outputs.iter().cloned().collect(),
+		}
+	}
+}
+
+pub fn create_vm(memory: Memory, tier: Tier) -> VirtualMachine {
+	let memory_fingerprint = memory
+		.iter()
+		.map(|(address, value)| get_cell_hash(*address, *value))
+		.fold(0u64, u64::wrapping_add);
+	VirtualMachine {
+		memory,
+		memory_fingerprint,
+		pointer: 0,
+		inputs: Input::new(),
+		outputs: Output::new(),
+		relative_base: 0,
+		tier,
+		decoded: std::collections::HashMap::new(),
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::intcode_conformance::{run_suite, Execution, Feature};
+	use crate::rng::Rng;
+
+	const TIERS: [Tier; 2] = [Tier::Interpreter, Tier::Cached];
+
+	fn execute(
+		tier: Tier,
+		program: &[BigByte],
+		inputs: &[BigByte],
+	) -> Result<Execution, &'static str> {
+		let mut vm = create_vm((0..).zip(program.iter().cloned()).collect(), tier);
+		inputs.iter().for_each(|&input| vm.push_input(input));
+		vm.run()?;
+		let mut outputs = Vec::new();
+		while let Some(output) = vm.pop_output() {
+			outputs.push(output);
+		}
+		Ok(Execution {
+			outputs,
+			memory: (0..program.len() as BigByte).map(|i| vm.get_byte(i)).collect(),
+		})
+	}
+
+	#[test]
+	fn conformance() {
+		for &tier in TIERS.iter() {
+			let failures = run_suite(
+				&[
+					Feature::RelativeMode,
+					Feature::LargeNumbers,
+					Feature::GrowingMemory,
+					Feature::MultipleInputs,
+					Feature::OutputStream,
+					Feature::CheckedArithmetic,
+				],
+				|program, inputs| execute(tier, program, inputs),
+			);
+			assert!(failures.is_empty(), "{}", failures.join("\n"));
+		}
+	}
+
+	// Random data lives at DATA, loop counters at COUNTERS, far after the code.
+	// The relative base points at DATA so relative parameters stay in that area.
+	const DATA: BigByte = 1000;
+	const DATA_SIZE: BigByte = 16;
+	const COUNTERS: BigByte = 1100;
+	const ARITHMETIC: [BigByte; 4] = [1, 2, 7, 8];
+
+	type Parameter = (BigByte, BigByte);
+
+	/*
+	 * Builds programs that always terminate: jumps only go forward, except for
+	 * loop back edges guarded by a counter that nothing else writes to. Code is
+	 * only ever modified by swapping the opcode of an arithmetic instruction
+	 * for another one of the same length.
+	 */
+	struct Generator {
+		rng: Rng,
+		code: Vec<BigByte>,
+		arithmetic: Vec<usize>,
+		loops: BigByte,
+		faulty: bool,
+	}
+
+	impl Generator {
+		fn read_parameter(&mut self) -> Parameter {
+			match self.rng.below(3) {
+				0 => (0, DATA + self.rng.range(0, DATA_SIZE - 1)),
+				1 => (1, self.rng.range(-20, 20)),
+				_ => (2, self.rng.range(0, DATA_SIZE - 1)),
+			}
+		}
+
+		fn write_parameter(&mut self) -> Parameter {
+			if self.faulty && self.rng.below(40) == 0 {
+				return (1, DATA);
+			}
+			match self.rng.below(2) {
+				0 => (0, DATA + self.rng.range(0, DATA_SIZE - 1)),
+				_ => (2, self.rng.range(0, DATA_SIZE - 1)),
+			}
+		}
+
+		fn emit(&mut self, op_code: BigByte, parameters: &[Parameter]) {
+			let modes = parameters.iter().rev().fold(0, |acc, (mode, _)| acc * 10 + mode);
+			self.code.push(modes * 100 + op_code);
+			self.code.extend(parameters.iter().map(|(_, value)| *value));
+		}
+
+		fn straight(&mut self) {
+			match self.rng.below(9) {
+				0..=4 => {
+					let op_code = ARITHMETIC[self.rng.below(4) as usize];
+					let parameters = [
+						self.read_parameter(),
+						self.read_parameter(),
+						self.write_parameter(),
+					];
+					self.arithmetic.push(self.code.len());
+					self.emit(op_code, &parameters);
+				}
+				5 => {
+					let parameter = self.write_parameter();
+					self.emit(3, &[parameter]);
+				}
+				6 => {
+					let parameter = self.read_parameter();
+					self.emit(4, &[parameter]);
+				}
+				7 if !self.arithmetic.is_empty() => {
+					let target = self.arithmetic[self.rng.below(self.arithmetic.len() as u64) as usize];
+					let current = self.code[target];
+					let replacement = current - current % 100 + ARITHMETIC[self.rng.below(4) as usize];
+					self.emit(1, &[(1, replacement), (1, 0), (0, target as BigByte)]);
+				}
+				_ => {
+					let offset = self.rng.range(-8, 8);
+					self.emit(9, &[(1, offset)]);
+					let parameter = self.read_parameter();
+					self.emit(4, &[parameter]);
+					self.emit(9, &[(1, -offset)]);
+				}
+			}
+		}
+
+		fn instruction(&mut self) {
+			if self.rng.below(6) > 0 {
+				return self.straight();
+			}
+			let op_code = 5 + self.rng.below(2) as BigByte;
+			let condition = self.read_parameter();
+			self.emit(op_code, &[condition, (1, 0)]);
+			let target = self.code.len() - 1;
+			for _ in 0..self.rng.range(1, 3) {
+				self.straight();
+			}
+			self.code[target] = self.code.len() as BigByte;
+		}
+
+		fn block(&mut self) {
+			let length = self.rng.range(1, 6);
+			if self.rng.below(2) == 0 {
+				for _ in 0..length {
+					self.instruction();
+				}
+				return;
+			}
+			let counter = COUNTERS + self.loops;
+			self.loops += 1;
+			let iterations = self.rng.range(1, 4);
+			self.emit(1, &[(1, iterations), (1, 0), (0, counter)]);
+			let start = self.code.len() as BigByte;
+			for _ in 0..length {
+				self.instruction();
+			}
+			self.emit(1, &[(0, counter), (1, -1), (0, counter)]);
+			self.emit(5, &[(0, counter), (1, start)]);
+		}
+
+		fn program(&mut self) -> Memory {
+			self.emit(9, &[(1, DATA)]);
+			for _ in 0..self.rng.range(1, 4) {
+				self.block();
+			}
+			self.code.push(99);
+			let mut memory: Memory = (0..).zip(self.code.iter().cloned()).collect();
+			for address in DATA..DATA + DATA_SIZE {
+				memory.insert(address, self.rng.range(-20, 20));
+			}
+			memory
+		}
+	}
+
+	fn run_tier(
+		tier: Tier,
+		memory: &Memory,
+		inputs: &[BigByte],
+	) -> (Result<(), &'static str>, Vec<BigByte>, VirtualMachine) {
+		let mut vm = create_vm(memory.clone(), tier);
+		inputs.iter().for_each(|&input| vm.push_input(input));
+		let result = vm.run();
+		let mut outputs = Vec::new();
+		while let Some(output) = vm.pop_output() {
+			outputs.push(output);
+		}
+		(result, outputs, vm)
+	}
+
+	#[test]
+	fn tiers_agree_on_random_programs() {
+		for seed in 1..=500u64 {
+			let mut rng = Rng::new(seed.wrapping_mul(0x9e37_79b9_7f4a_7c15));
+			let faulty = rng.below(4) == 0;
+			let mut generator = Generator {
+				rng,
+				code: Vec::new(),
+				arithmetic: Vec::new(),
+				loops: 0,
+				faulty,
+			};
+			let memory = generator.program();
+			let inputs: Vec<BigByte> = (0..generator.rng.range(0, 6))
+				.map(|_| generator.rng.range(-20, 20))
+				.collect();
+			let (expected, expected_outputs, reference) = run_tier(Tier::Interpreter, &memory, &inputs);
+			let (result, outputs, vm) = run_tier(Tier::Cached, &memory, &inputs);
+			let program = generator.code;
+			assert_eq!(result, expected, "seed {}: {:?}", seed, program);
+			assert_eq!(outputs, expected_outputs, "seed {}: {:?}", seed, program);
+			assert!(vm.memory == reference.memory, "seed {}: {:?}", seed, program);
+			assert_eq!(vm.pointer, reference.pointer, "seed {}: {:?}", seed, program);
+			assert_eq!(
+				vm.relative_base, reference.relative_base,
+				"seed {}: {:?}",
+				seed, program
+			);
+		}
+	}
+}
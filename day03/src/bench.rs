@@ -0,0 +1,54 @@
+use crate::{
+  closest_intersection_distance,
+  get_crossings_naive,
+  make_wires_segments,
+  min_step_to_intersection,
+  sweep,
+  Direction,
+  Movement,
+};
+use common::bench::time;
+use common::rng::Rng;
+
+/*
+ * Wire turning at every movement like the puzzle ones, the lengths drawn so
+ * that it wanders around the origin without drifting too far.
+ */
+pub fn generate_wire(rng : &mut Rng, segments : usize, max_steps : i32) -> Vec<Movement> {
+  let mut movements = Vec::with_capacity(segments);
+  let (mut x, mut y) = (0, 0);
+  for i in 0..segments {
+    let steps = rng.range(1, max_steps);
+    let towards_origin = rng.range(0, 2) > 0;
+    let direction = if i % 2 == 0 {
+      if (x > 0) == towards_origin { x -= steps; Direction::Left } else { x += steps; Direction::Right }
+    } else if (y > 0) == towards_origin {
+      y -= steps;
+      Direction::Down
+    } else {
+      y += steps;
+      Direction::Up
+    };
+    movements.push(Movement { direction, steps });
+  }
+  movements
+}
+
+// Times both crossing searches on two random wires and checks they agree.
+pub fn run(segments : usize) {
+  let mut rng = Rng::new(segments as u64);
+  let wires = make_wires_segments(vec![
+    generate_wire(&mut rng, segments, 1000),
+    generate_wire(&mut rng, segments, 1000),
+  ]);
+  println!("Bench: 2 wires of {} segments", segments);
+  let (naive, naive_time) = time(|| get_crossings_naive(&wires[0], &wires[1]));
+  println!("Bench: naive: {} crossings in {:.2?}", naive.len(), naive_time);
+  let (swept, sweep_time) = time(|| sweep::get_crossings(&wires[0], &wires[1]));
+  println!("Bench: sweep: {} crossings in {:.2?}", swept.len(), sweep_time);
+  let results = |crossings| (
+    closest_intersection_distance(crossings),
+    min_step_to_intersection(&wires[0], &wires[1], crossings),
+  );
+  assert!(naive.len() == swept.len() && results(&naive) == results(&swept), "Crossing searches disagree");
+}
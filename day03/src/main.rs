@@ -1,10 +1,16 @@
 use std::{
+  collections::{BTreeMap, BTreeSet},
   env,
   fs,
   io::{prelude::*},
   path,
 };
 
+use common::svg::{self, Svg};
+
+mod bench;
+mod sweep;
+
 enum Direction {
   Up,
   Left,
@@ -17,35 +23,76 @@ struct Movement {
   steps: i32,
 }
 
-fn parse_movement(text : &str) -> Movement {
-  let mut it = text.chars();
-  Movement {
-    direction : match it.next().unwrap() {
-      'D' => Direction::Down,
-      'U' => Direction::Up,
-      'L' => Direction::Left,
-      'R' => Direction::Right,
-      _ => panic!("Invalid direction found"),
-    },
-    steps : it.as_str().parse::<i32>().unwrap()
+// Wires and movements are numbered from 1, as a text editor would.
+#[derive(Debug, PartialEq)]
+enum ParseError {
+  EmptyMovement { wire : usize, movement : usize },
+  InvalidDirection { wire : usize, movement : usize, found : char },
+  InvalidSteps { wire : usize, movement : usize, found : String },
+  NotEnoughWires(usize),
+}
+
+impl std::fmt::Display for ParseError {
+  fn fmt(&self, f : &mut std::fmt::Formatter) -> std::fmt::Result {
+    match self {
+      ParseError::EmptyMovement { wire, movement } => {
+        write!(f, "wire {}, movement {}: empty movement", wire, movement)
+      },
+      ParseError::InvalidDirection { wire, movement, found } => {
+        write!(f, "wire {}, movement {}: invalid direction '{}', expected U, D, L or R", wire, movement, found)
+      },
+      ParseError::InvalidSteps { wire, movement, found } => {
+        write!(f, "wire {}, movement {}: invalid number of steps '{}'", wire, movement, found)
+      },
+      ParseError::NotEnoughWires(count) => write!(f, "at least two wires are needed, found {}", count),
+    }
   }
 }
 
-fn parse_line(text : &str) -> Vec<Movement> {
-  text.trim().split(",")
-  .map(|token| parse_movement(token))
+fn parse_movement(text : &str, wire : usize, movement : usize) -> Result<Movement, ParseError> {
+  let mut it = text.chars();
+  let direction = match it.next() {
+    Some('D') => Direction::Down,
+    Some('U') => Direction::Up,
+    Some('L') => Direction::Left,
+    Some('R') => Direction::Right,
+    Some(found) => return Err(ParseError::InvalidDirection { wire, movement, found }),
+    None => return Err(ParseError::EmptyMovement { wire, movement }),
+  };
+  let steps = match it.as_str().parse::<i32>() {
+    Ok(steps) if steps >= 0 => steps,
+    _ => return Err(ParseError::InvalidSteps { wire, movement, found : it.as_str().to_string() }),
+  };
+  Ok(Movement { direction, steps })
+}
+
+fn parse_line(text : &str, wire : usize) -> Result<Vec<Movement>, ParseError> {
+  text.trim().split(',')
+  .enumerate()
+  .map(|(i, token)| parse_movement(token.trim(), wire, i + 1))
   .collect()
 }
 
-fn parse_file(filename : impl AsRef<path::Path>) -> Vec<Vec<Movement>> {
+fn parse_wires(contents : &str) -> Result<Vec<Vec<Movement>>, ParseError> {
+  let wires = contents.trim().lines()
+    .enumerate()
+    .map(|(i, line)| parse_line(line, i + 1))
+    .collect::<Result<Vec<_>, _>>()?;
+  if wires.len() < 2 {
+    return Err(ParseError::NotEnoughWires(wires.len()));
+  }
+  Ok(wires)
+}
+
+fn parse_file(filename : impl AsRef<path::Path>) -> Result<Vec<Vec<Movement>>, ParseError> {
   let mut file = fs::File::open(filename).expect("File not found");
   let mut contents = String::new();
   file.read_to_string(&mut contents)
     .expect("Could not read file");
-    contents.trim().split("\n").map(|line| parse_line(line)).collect()
+  parse_wires(&contents)
 }
 
-#[derive(Copy, Clone)]
+#[derive(Copy, Clone, PartialEq)]
 struct Point {
   x : i32,
   y : i32,
@@ -56,6 +103,17 @@ struct Line {
   p2 : Point,
 }
 
+impl Line {
+  // Single points count as horizontal, never as vertical.
+  fn is_horizontal(&self) -> bool {
+    self.p1.y == self.p2.y
+  }
+
+  fn is_vertical(&self) -> bool {
+    self.p1.x == self.p2.x && self.p1.y != self.p2.y
+  }
+}
+
 fn make_point(movement : &Movement, start : &Point) -> Point {
   match movement.direction {
     Direction::Down  => Point { x: start.x, y : start.y - movement.steps },
@@ -84,90 +142,432 @@ fn make_wires_segments(wires_movements : Vec<Vec<Movement>>) -> Vec<Vec<Line>> {
   wires
 }
 
-fn between(r : i32, n1 : i32, n2 : i32) -> bool {
-  (r < n1 && r > n2) || (r > n1 && r < n2)
+// All the wires start from the origin, which may or may not count as a crossing.
+#[derive(Clone, Copy)]
+enum OriginPolicy {
+  Exclude,
+  Include,
 }
 
-fn get_intersection(segment1 : &Line, segment2 : &Line) -> Result<Point, &'static str> {
-  if segment1.p1.x == 0 && segment2.p1.x == 0 && segment2.p1.y == 0 && segment2.p1.y == 0 {
-    return Err("First segments")
+fn get_origin_policy(name : &str) -> Result<OriginPolicy, &'static str> {
+  match name {
+    "exclude" => Ok(OriginPolicy::Exclude),
+    "include" => Ok(OriginPolicy::Include),
+    _ => Err("Invalid origin policy, expected exclude or include"),
   }
-  if segment1.p1.x == segment1.p2.x
-    && segment2.p1.y == segment2.p2.y
-    && between(segment1.p1.x, segment2.p1.x, segment2.p2.x)
-    && between(segment2.p1.y, segment1.p1.y, segment1.p2.y) {
-    Ok(Point{ x : segment1.p1.x, y: segment2.p1.y})
-  } else if segment1.p1.y == segment1.p2.y
-    && segment2.p1.x == segment2.p2.x
-    && between(segment2.p1.x, segment1.p1.x, segment1.p2.x)
-    && between(segment1.p1.y, segment2.p1.y, segment2.p2.y) {
-    Ok(Point{ x : segment2.p1.x, y: segment1.p1.y})
-  } else {
-    Err("Not intersection")
-  }
-}
-
-fn closest_intersection_distance(wires_segments : &Vec<Vec<Line>>) -> i32 {
-  let mut closest = -1;
-  for segment1 in &wires_segments[0] {
-    for segment2 in &wires_segments[1] {
-      match get_intersection(&segment1, &segment2) {
-        Ok(intersection) => {
-          let distance = intersection.x.abs() + intersection.y.abs();
-          if closest == -1 || distance < closest {
-            closest = distance;
-          }
-        },
-        Err(_e) => (),
+}
+
+fn is_accepted(point : &Point, policy : OriginPolicy) -> bool {
+  match policy {
+    OriginPolicy::Exclude => point.x != 0 || point.y != 0,
+    OriginPolicy::Include => true,
+  }
+}
+
+/*
+ * Every lattice point two segments share. The points of an axis aligned
+ * segment fill its bounding box, so the shared points fill the intersection
+ * of both boxes: a single point when the segments are perpendicular or touch,
+ * a whole stretch when they overlap on the same line.
+ */
+fn get_shared_points(segment1 : &Line, segment2 : &Line) -> Vec<Point> {
+  let left = segment1.p1.x.min(segment1.p2.x).max(segment2.p1.x.min(segment2.p2.x));
+  let right = segment1.p1.x.max(segment1.p2.x).min(segment2.p1.x.max(segment2.p2.x));
+  let bottom = segment1.p1.y.min(segment1.p2.y).max(segment2.p1.y.min(segment2.p2.y));
+  let top = segment1.p1.y.max(segment1.p2.y).min(segment2.p1.y.max(segment2.p2.y));
+  let mut points = Vec::new();
+  for x in left..=right {
+    for y in bottom..=top {
+      points.push(Point { x, y });
+    }
+  }
+  points
+}
+
+// Where two wires cross, with the indices of the segments crossing there.
+struct Crossing {
+  point : Point,
+  segment1 : usize,
+  segment2 : usize,
+}
+
+/*
+ * A point lying on several segments of a wire, like the joint of two
+ * segments, is found once per segment. Only the crossing with the first
+ * segment of each wire is kept, the one reached first walking along them.
+ */
+fn remove_duplicate_crossings(crossings : &mut Vec<Crossing>) {
+  crossings.sort_unstable_by_key(|crossing| (crossing.point.x, crossing.point.y, crossing.segment1, crossing.segment2));
+  crossings.dedup_by_key(|crossing| crossing.point);
+}
+
+// Compares every segment of the first wire to every segment of the second.
+fn get_crossings_naive(wire1 : &[Line], wire2 : &[Line]) -> Vec<Crossing> {
+  let mut crossings = Vec::new();
+  for (segment1, line1) in wire1.iter().enumerate() {
+    for (segment2, line2) in wire2.iter().enumerate() {
+      for point in get_shared_points(line1, line2) {
+        crossings.push(Crossing { point, segment1, segment2 });
       }
     }
   }
-  closest
+  remove_duplicate_crossings(&mut crossings);
+  crossings
+}
+
+fn closest_intersection_distance(crossings : &[Crossing]) -> i32 {
+  crossings
+    .iter()
+    .map(|crossing| crossing.point.x.abs() + crossing.point.y.abs())
+    .min()
+    .unwrap_or(-1)
 }
 
 fn segment_size(line : &Line) -> i32 {
   if line.p1.x == line.p2.x {
-    return (line.p1.y - line.p2.y).abs()
+    (line.p1.y - line.p2.y).abs()
   } else {
-    return (line.p1.x - line.p2.x).abs()
-  }
-}
-
-fn min_step_to_intersection(wires_segments : &Vec<Vec<Line>>) -> i32 {
-  let mut min_steps = -1;
-  let mut steps1 = 0;
-  for segment1 in &wires_segments[0] {
-    let mut steps2 = 0;
-    for segment2 in &wires_segments[1] {
-      match get_intersection(&segment1, &segment2) {
-        Ok(intersection) => {
-          let distance = steps1 + steps2
-            + segment_size(&Line { p1 : segment1.p1, p2 : intersection })
-            + segment_size(&Line { p1 : segment2.p1, p2 : intersection });
-          if min_steps == -1 || distance < min_steps {
-            min_steps = distance;
-          }
-        },
-        Err(_e) => (),
+    (line.p1.x - line.p2.x).abs()
+  }
+}
+
+// Steps walked along a wire before reaching the start of each segment.
+fn get_steps_before(wire : &[Line]) -> Vec<i32> {
+  wire
+    .iter()
+    .scan(0, |steps, line| {
+      let before = *steps;
+      *steps += segment_size(line);
+      Some(before)
+    })
+    .collect()
+}
+
+// Steps walked along a wire to reach a point of one of its segments.
+fn get_steps_at(wire : &[Line], steps_before : &[i32], segment : usize, point : Point) -> i32 {
+  steps_before[segment] + segment_size(&Line { p1 : wire[segment].p1, p2 : point })
+}
+
+fn min_step_to_intersection(wire1 : &[Line], wire2 : &[Line], crossings : &[Crossing]) -> i32 {
+  let steps1 = get_steps_before(wire1);
+  let steps2 = get_steps_before(wire2);
+  crossings
+    .iter()
+    .map(|crossing| {
+      get_steps_at(wire1, &steps1, crossing.segment1, crossing.point)
+        + get_steps_at(wire2, &steps2, crossing.segment2, crossing.point)
+    })
+    .min()
+    .unwrap_or(-1)
+}
+
+// A point a wire goes through again, `steps` being when it comes back.
+struct Revisit {
+  point : Point,
+  steps : i32,
+}
+
+/*
+ * Points a wire goes through more than once, each with the first time it
+ * comes back, sorted by that time. Points shared by following segments are
+ * reached once only, unless the wire goes back on its tracks.
+ */
+fn get_revisits(wire : &[Line]) -> Vec<Revisit> {
+  let steps_before = get_steps_before(wire);
+  let mut revisits : Vec<Revisit> = Vec::new();
+  for (segment1, line1) in wire.iter().enumerate() {
+    for (segment2, line2) in wire.iter().enumerate().skip(segment1 + 1) {
+      for point in get_shared_points(line1, line2) {
+        let first = get_steps_at(wire, &steps_before, segment1, point);
+        let again = get_steps_at(wire, &steps_before, segment2, point);
+        if first != again {
+          revisits.push(Revisit { point, steps : again });
+        }
       }
-      steps2 += segment_size(&segment2);
     }
-    steps1 += segment_size(&segment1);
   }
-  min_steps
+  revisits.sort_unstable_by_key(|revisit| (revisit.point.x, revisit.point.y, revisit.steps));
+  revisits.dedup_by_key(|revisit| revisit.point);
+  revisits.sort_by_key(|revisit| revisit.steps);
+  revisits
+}
+
+const WIRE_COLORS : [&str; 4] = ["#1f77b4", "#d62728", "#2ca02c", "#9467bd"];
+
+// Crossings of two wires of the file, numbered from 0.
+struct Pair {
+  wire1 : usize,
+  wire2 : usize,
+  crossings : Vec<Crossing>,
+  closest : i32,
+  min_steps : i32,
+}
+
+fn get_pairs(wires_segments : &[Vec<Line>], policy : OriginPolicy) -> Vec<Pair> {
+  let mut pairs = Vec::new();
+  for wire1 in 0..wires_segments.len() {
+    for wire2 in wire1 + 1..wires_segments.len() {
+      let (segments1, segments2) = (&wires_segments[wire1], &wires_segments[wire2]);
+      let mut crossings = sweep::get_crossings(segments1, segments2);
+      crossings.retain(|crossing| is_accepted(&crossing.point, policy));
+      pairs.push(Pair {
+        wire1,
+        wire2,
+        closest : closest_intersection_distance(&crossings),
+        min_steps : min_step_to_intersection(segments1, segments2, &crossings),
+        crossings,
+      });
+    }
+  }
+  pairs
+}
+
+// Smallest result over the pairs, -1 when no pair crosses.
+fn get_best(results : impl Iterator<Item = i32>) -> i32 {
+  results.filter(|result| *result >= 0).min().unwrap_or(-1)
+}
+
+/*
+ * Points where at least three wires cross, with the wires crossing there in
+ * increasing order. A wire going through a crossing of two others crosses at
+ * least one of them, so every such point shows up in the pair crossings.
+ */
+fn get_multiple_crossings(pairs : &[Pair]) -> Vec<(Point, Vec<usize>)> {
+  let mut wires_at = BTreeMap::new();
+  for pair in pairs {
+    for crossing in &pair.crossings {
+      let wires = wires_at.entry((crossing.point.x, crossing.point.y)).or_insert_with(BTreeSet::new);
+      wires.insert(pair.wire1);
+      wires.insert(pair.wire2);
+    }
+  }
+  wires_at
+    .into_iter()
+    .filter(|(_, wires)| wires.len() >= 3)
+    .map(|((x, y), wires)| (Point { x, y }, wires.into_iter().collect()))
+    .collect()
+}
+
+// The y axis of the puzzle points up, the one of SVG down.
+fn get_svg_point(point : &Point) -> (f64, f64) {
+  (point.x as f64, -point.y as f64)
+}
+
+fn get_svg(wires_segments : &[Vec<Line>], pairs : &[Pair], multiple_crossings : &[(Point, Vec<usize>)]) -> Svg {
+  let mut svg = Svg::new();
+  for (i, wire) in wires_segments.iter().enumerate() {
+    let mut points = vec![(0.0, 0.0)];
+    points.extend(wire.iter().map(|segment| get_svg_point(&segment.p2)));
+    svg.add_polyline(&points, WIRE_COLORS[i % WIRE_COLORS.len()], 1.0);
+  }
+  let radius = svg.get_span() / 300.0;
+  for crossing in pairs.iter().flat_map(|pair| &pair.crossings) {
+    let (x, y) = get_svg_point(&crossing.point);
+    svg.add_circle(x, y, radius, "#ff7f0e");
+  }
+  for (point, _) in multiple_crossings {
+    let (x, y) = get_svg_point(point);
+    svg.add_circle(x, y, radius * 2.0, "#e377c2");
+  }
+  svg.add_circle(0.0, 0.0, radius * 1.5, "#000000");
+  svg
+}
+
+fn print_revisits(wires_segments : &[Vec<Line>]) {
+  for (i, wire) in wires_segments.iter().enumerate() {
+    let revisits = get_revisits(wire);
+    match revisits.first() {
+      Some(first) => println!(
+        "Wire {}: {} self-intersections, first revisit after {} steps at {},{}",
+        i + 1, revisits.len(), first.steps, first.point.x, first.point.y
+      ),
+      None => println!("Wire {}: no self-intersection", i + 1),
+    }
+  }
+}
+
+struct Options {
+  svg : Option<String>,
+  bench : Option<usize>,
+  revisits : bool,
+  origin : OriginPolicy,
+}
+
+fn parse_options(args : &[String]) -> Result<Options, &'static str> {
+  let mut options = Options {
+    svg : None,
+    bench : None,
+    revisits : false,
+    origin : OriginPolicy::Exclude,
+  };
+  let mut it = args.iter();
+  while let Some(arg) = it.next() {
+    match arg.as_str() {
+      "--origin" => options.origin = get_origin_policy(it.next().ok_or("Missing value after --origin")?)?,
+      "--revisits" => options.revisits = true,
+      "--svg" => options.svg = Some(it.next().ok_or("Missing value after --svg")?.clone()),
+      "--bench" => {
+        options.bench = Some(it.next().ok_or("Missing value after --bench")?
+          .parse::<usize>().map_err(|_| "Invalid number of segments")?)
+      },
+      _ => return Err("Unknown option"),
+    }
+  }
+  Ok(options)
 }
 
 fn main() {
   let args: Vec<String> = env::args().collect();
-  if args.len() < 2 {
-    panic!("Not enough arguments");
+  // Benchmarks generate their own wires, so they need no input file.
+  let (filename, options) = match args.get(1) {
+    Some(arg) if arg.starts_with("--") => (None, parse_options(&args[1..])),
+    Some(filename) => (Some(filename), parse_options(&args[2..])),
+    None => panic!("Not enough arguments"),
+  };
+  let options = options.expect("Invalid arguments");
+  if let Some(segments) = options.bench {
+    bench::run(segments);
+    return;
   }
-  let filename = &args[1];
+  let filename = filename.expect("Not enough arguments");
   println!("Loading file {}", filename);
-  let content = parse_file(filename);
+  let content = match parse_file(filename) {
+    Ok(content) => content,
+    Err(e) => panic!("Invalid wires: {}", e),
+  };
   let wires_segments = make_wires_segments(content);
-  let result1 = closest_intersection_distance(&wires_segments);
+  let pairs = get_pairs(&wires_segments, options.origin);
+  let result1 = get_best(pairs.iter().map(|pair| pair.closest));
   println!("Part1: closest intersection: {}", result1);
-  let result2 = min_step_to_intersection(&wires_segments);
+  let result2 = get_best(pairs.iter().map(|pair| pair.min_steps));
   println!("Part2: minimum steps to intersection: {}", result2);
+  let multiple_crossings = get_multiple_crossings(&pairs);
+  if wires_segments.len() > 2 {
+    for pair in &pairs {
+      println!(
+        "Wires {} and {}: {} crossings, closest intersection: {}, minimum steps to intersection: {}",
+        pair.wire1 + 1, pair.wire2 + 1, pair.crossings.len(), pair.closest, pair.min_steps
+      );
+    }
+    println!("Crossings of three wires or more: {}", multiple_crossings.len());
+    for (point, wires) in &multiple_crossings {
+      let wires : Vec<String> = wires.iter().map(|wire| (wire + 1).to_string()).collect();
+      println!("  {},{}: wires {}", point.x, point.y, wires.join(", "));
+    }
+  }
+  if options.revisits {
+    print_revisits(&wires_segments);
+  }
+  if let Some(filename) = &options.svg {
+    let svg = get_svg(&wires_segments, &pairs, &multiple_crossings);
+    svg::write_svg(&svg, 1000, filename).expect("Could not export the wires");
+    println!("Wires written to {}", filename);
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn get_wires(text : &str) -> Vec<Vec<Line>> {
+    make_wires_segments(parse_wires(text).unwrap())
+  }
+
+  #[test]
+  fn checks_every_pair_of_wires() {
+    let wires = get_wires("R8,U5,L5,D3\nU7,R6,D4,L4\nR3,U10\nU2,R10");
+    let pairs = get_pairs(&wires, OriginPolicy::Exclude);
+    let results : Vec<(usize, usize, i32, i32)> = pairs
+      .iter()
+      .map(|pair| (pair.wire1, pair.wire2, pair.closest, pair.min_steps))
+      .collect();
+    assert_eq!(results, vec![
+      (0, 1, 6, 30),
+      (0, 2, 1, 2),
+      (0, 3, 5, 20),
+      (1, 2, 6, 20),
+      (1, 3, 1, 2),
+      (2, 3, 5, 10),
+    ]);
+    let multiple_crossings : Vec<(i32, i32, Vec<usize>)> = get_multiple_crossings(&pairs)
+      .into_iter()
+      .map(|(point, wires)| (point.x, point.y, wires))
+      .collect();
+    assert_eq!(multiple_crossings, vec![(3, 2, vec![0, 2, 3]), (3, 3, vec![0, 1, 2])]);
+  }
+
+  fn get_crossing_points(text : &str) -> Vec<(i32, i32)> {
+    let wires = get_wires(text);
+    sweep::get_crossings(&wires[0], &wires[1])
+      .iter()
+      .filter(|crossing| is_accepted(&crossing.point, OriginPolicy::Exclude))
+      .map(|crossing| (crossing.point.x, crossing.point.y))
+      .collect()
+  }
+
+  #[test]
+  fn finds_touching_and_overlapping_wires() {
+    assert_eq!(get_crossing_points("U1,R4,D3\nR1,U2,R1,D1"), vec![(1, 1), (2, 1)]);
+    assert_eq!(get_crossing_points("U2,R5\nR1,U2,R2,D2"), vec![(1, 2), (2, 2), (3, 2)]);
+  }
+
+  #[test]
+  fn finds_revisits() {
+    let wires = get_wires("R4,U2,L2,D4\nR3,L5");
+    let revisits : Vec<(i32, i32, i32)> = get_revisits(&wires[0])
+      .iter()
+      .map(|revisit| (revisit.point.x, revisit.point.y, revisit.steps))
+      .collect();
+    assert_eq!(revisits, vec![(2, 0, 10)]);
+    let revisits : Vec<(i32, i32, i32)> = get_revisits(&wires[1])
+      .iter()
+      .map(|revisit| (revisit.point.x, revisit.point.y, revisit.steps))
+      .collect();
+    assert_eq!(revisits, vec![(2, 0, 4), (1, 0, 5), (0, 0, 6)]);
+  }
+
+  fn get_results(text : &str, policy : OriginPolicy) -> (i32, i32, usize) {
+    let wires = get_wires(text);
+    let pairs = get_pairs(&wires, policy);
+    (pairs[0].closest, pairs[0].min_steps, pairs[0].crossings.len())
+  }
+
+  #[test]
+  fn solves_published_examples() {
+    let examples = [
+      ("R8,U5,L5,D3\nU7,R6,D4,L4", 6, 30),
+      ("R75,D30,R83,U83,L12,D49,R71,U7,L72\nU62,R66,U55,R34,D71,R55,D58,R83", 159, 610),
+      ("R98,U47,R26,D63,R33,U87,L62,D20,R33,U53,R51\nU98,R91,D20,R16,D67,R40,U7,R15,U6,R7", 135, 410),
+    ];
+    for (text, closest, min_steps) in examples.iter() {
+      let (result1, result2, _) = get_results(text, OriginPolicy::Exclude);
+      assert_eq!((result1, result2), (*closest, *min_steps), "{}", text);
+      let wires = get_wires(text);
+      let naive = get_crossings_naive(&wires[0], &wires[1]);
+      let naive : Vec<&Crossing> = naive.iter().filter(|crossing| is_accepted(&crossing.point, OriginPolicy::Exclude)).collect();
+      assert_eq!(naive.iter().map(|crossing| crossing.point.x.abs() + crossing.point.y.abs()).min(), Some(*closest));
+    }
+  }
+
+  #[test]
+  fn applies_origin_policy() {
+    assert_eq!(get_results("R8,U5,L5,D3\nU7,R6,D4,L4", OriginPolicy::Include), (0, 0, 3));
+    // The first segments overlap, their shared points other than the origin cross.
+    assert_eq!(get_results("R5\nR3,U1", OriginPolicy::Exclude), (1, 2, 3));
+    // The wires cross along a segment starting on the y axis.
+    assert_eq!(get_results("R2,U2,L2,U3\nU5", OriginPolicy::Exclude), (2, 8, 4));
+    // Coming back to the origin is not a crossing either.
+    assert_eq!(get_results("R1,U1,L1,D1\nL1,D1", OriginPolicy::Exclude), (-1, -1, 0));
+  }
+
+  #[test]
+  fn reports_parse_errors() {
+    assert_eq!(parse_wires("R8,X5\nU7").err(), Some(ParseError::InvalidDirection { wire : 1, movement : 2, found : 'X' }));
+    assert_eq!(
+      parse_wires("R8\nU7,R-2").err(),
+      Some(ParseError::InvalidSteps { wire : 2, movement : 2, found : String::from("-2") })
+    );
+    assert_eq!(parse_wires("R8,,U1\nU7").err(), Some(ParseError::EmptyMovement { wire : 1, movement : 2 }));
+    assert_eq!(parse_wires("R8,U1").err(), Some(ParseError::NotEnoughWires(1)));
+  }
 }
@@ -0,0 +1,126 @@
+use crate::{remove_duplicate_crossings, Crossing, Line, Point};
+use std::collections::{BTreeSet, HashMap};
+
+// At a same x, segments starting enter before verticals are checked and
+// segments ending leave after, so that touching endpoints cross.
+const ENTER : u8 = 0;
+const CHECK : u8 = 1;
+const LEAVE : u8 = 2;
+
+fn get_crossing(swapped : bool, horizontal : usize, vertical : usize, point : Point) -> Crossing {
+  let (segment1, segment2) = if swapped { (vertical, horizontal) } else { (horizontal, vertical) };
+  Crossing { point, segment1, segment2 }
+}
+
+/*
+ * Crossings of the horizontal segments of a wire with the vertical segments
+ * of another, sweeping a vertical line from left to right: the horizontal
+ * segments under the line are kept ordered by y, so each vertical segment
+ * only visits those it crosses. `swapped` tells the horizontal segments come
+ * from the second wire.
+ */
+fn sweep(horizontals : &[Line], verticals : &[Line], swapped : bool, crossings : &mut Vec<Crossing>) {
+  let mut events = Vec::new();
+  for (i, segment) in horizontals.iter().enumerate().filter(|(_, segment)| segment.is_horizontal()) {
+    events.push((segment.p1.x.min(segment.p2.x), ENTER, i));
+    events.push((segment.p1.x.max(segment.p2.x), LEAVE, i));
+  }
+  for (j, segment) in verticals.iter().enumerate().filter(|(_, segment)| segment.is_vertical()) {
+    events.push((segment.p1.x, CHECK, j));
+  }
+  events.sort_unstable();
+  let mut active = BTreeSet::new();
+  for (x, kind, index) in events {
+    match kind {
+      ENTER => {
+        active.insert((horizontals[index].p1.y, index));
+      },
+      LEAVE => {
+        active.remove(&(horizontals[index].p1.y, index));
+      },
+      _ => {
+        let segment = &verticals[index];
+        let bottom = segment.p1.y.min(segment.p2.y);
+        let top = segment.p1.y.max(segment.p2.y);
+        for &(y, i) in active.range((bottom, 0)..=(top, usize::MAX)) {
+          crossings.push(get_crossing(swapped, i, index, Point { x, y }));
+        }
+      },
+    }
+  }
+}
+
+/*
+ * Points shared by segments of both wires lying on the same line. Segments
+ * are grouped by line and sorted by their start along it, so each segment
+ * only meets the segments of the other wire still running when it starts.
+ */
+fn overlap(wire1 : &[Line], wire2 : &[Line], horizontal : bool, crossings : &mut Vec<Crossing>) {
+  let mut lines : HashMap<i32, Vec<(i32, i32, usize, usize)>> = HashMap::new();
+  for (wire, segments) in [wire1, wire2].iter().enumerate() {
+    for (index, segment) in segments.iter().enumerate() {
+      let (line, start, end) = if horizontal && segment.is_horizontal() {
+        (segment.p1.y, segment.p1.x.min(segment.p2.x), segment.p1.x.max(segment.p2.x))
+      } else if !horizontal && segment.is_vertical() {
+        (segment.p1.x, segment.p1.y.min(segment.p2.y), segment.p1.y.max(segment.p2.y))
+      } else {
+        continue;
+      };
+      lines.entry(line).or_default().push((start, end, wire, index));
+    }
+  }
+  for (line, segments) in lines.iter_mut() {
+    segments.sort_unstable();
+    let mut running : [Vec<(i32, usize)>; 2] = [Vec::new(), Vec::new()];
+    for &(start, end, wire, index) in segments.iter() {
+      running[1 - wire].retain(|(other_end, _)| *other_end >= start);
+      for &(other_end, other) in &running[1 - wire] {
+        for position in start..=end.min(other_end) {
+          let point = if horizontal { Point { x : position, y : *line } } else { Point { x : *line, y : position } };
+          let (segment1, segment2) = if wire == 0 { (index, other) } else { (other, index) };
+          crossings.push(Crossing { point, segment1, segment2 });
+        }
+      }
+      running[wire].push((end, index));
+    }
+  }
+}
+
+// Same crossings as `get_crossings_naive`, in O((n + m) log(n + m) + k).
+pub fn get_crossings(wire1 : &[Line], wire2 : &[Line]) -> Vec<Crossing> {
+  let mut crossings = Vec::new();
+  sweep(wire1, wire2, false, &mut crossings);
+  sweep(wire2, wire1, true, &mut crossings);
+  overlap(wire1, wire2, true, &mut crossings);
+  overlap(wire1, wire2, false, &mut crossings);
+  remove_duplicate_crossings(&mut crossings);
+  crossings
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::bench::generate_wire;
+  use common::rng::Rng;
+  use crate::{get_crossings_naive, make_wires_segments};
+
+  fn get_points(crossings : &[Crossing]) -> Vec<(i32, i32, usize, usize)> {
+    crossings
+      .iter()
+      .map(|crossing| (crossing.point.x, crossing.point.y, crossing.segment1, crossing.segment2))
+      .collect()
+  }
+
+  // Short steps make wires touch, overlap and run along each other often.
+  #[test]
+  fn agrees_with_naive_search() {
+    let mut rng = Rng::new(2019);
+    for _ in 0..200 {
+      let wires = make_wires_segments(vec![generate_wire(&mut rng, 30, 4), generate_wire(&mut rng, 30, 4)]);
+      assert_eq!(
+        get_points(&get_crossings(&wires[0], &wires[1])),
+        get_points(&get_crossings_naive(&wires[0], &wires[1]))
+      );
+    }
+  }
+}
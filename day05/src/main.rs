@@ -23,6 +23,20 @@ enum Mode {
 struct Parameters {
   one : Mode,
   two : Mode,
+  three : Mode,
+}
+
+// Parameters written to are addresses, they cannot be in immediate mode.
+fn get_address(content : &[i32], mode : &Mode, parameter : i32) -> Result<usize, &'static str> {
+  match mode {
+    Mode::Position => {
+      if parameter < 0 || (parameter as usize) >= content.len() {
+        return Err("result_id out of range");
+      }
+      Ok(parameter as usize)
+    },
+    Mode::Immediate => Err("Write parameter is in immediate mode"),
+  }
 }
 
 fn get_value(content : &Vec<i32>, mode : &Mode, parameter : i32) -> Result<i32, &'static str> {
@@ -40,39 +54,34 @@ fn get_value(content : &Vec<i32>, mode : &Mode, parameter : i32) -> Result<i32,
 }
 
 fn add(parameters : &Parameters, content : &mut Vec<i32>, id : &mut usize) -> Result<(), &'static str> {
-  let result_id = content[*id + 3] as usize;
+  let result_id = get_address(content, &parameters.three, content[*id + 3])?;
   let value_left = get_value(&content, &parameters.one, content[*id + 1])?;
   let value_right = get_value(&content, &parameters.two, content[*id + 2])?;
-  if result_id >= content.len() {
-    return Err("result_id out of range");
-  }
   content[result_id] = value_left + value_right;
   *id += 4;
   Ok(())
 }
 
 fn mult(parameters : &Parameters, content : &mut Vec<i32>, id : &mut usize) -> Result<(), &'static str> {
-  let result_id = content[*id + 3] as usize;
+  let result_id = get_address(content, &parameters.three, content[*id + 3])?;
   let value_left = get_value(&content, &parameters.one, content[*id + 1])?;
   let value_right = get_value(&content, &parameters.two, content[*id + 2])?;
-  if result_id >= content.len() {
-    return Err("result_id out of range");
-  }
   content[result_id] = value_left * value_right;
   *id += 4;
   Ok(())
 }
 
-fn store_input(content : &mut Vec<i32>, input : i32, id: &mut usize) {
-  let ptr = content[*id + 1] as usize;
+fn store_input(parameters : &Parameters, content : &mut Vec<i32>, input : i32, id: &mut usize) -> Result<(), &'static str> {
+  let ptr = get_address(content, &parameters.one, content[*id + 1])?;
   content[ptr] = input;
   *id += 2;
+  Ok(())
 }
 
-fn store_output(content : &mut Vec<i32>, id: &mut usize, output : &mut i32) {
-  let ptr = content[*id + 1] as usize;
-  *output = content[ptr];
+fn store_output(parameters : &Parameters, content : &mut Vec<i32>, id: &mut usize, output : &mut i32) -> Result<(), &'static str> {
+  *output = get_value(&content, &parameters.one, content[*id + 1])?;
   *id += 2;
+  Ok(())
 }
 
 fn jump_if_true(parameters : &Parameters, content : &mut Vec<i32>, id : &mut usize) -> Result<(), &'static str> {
@@ -98,24 +107,18 @@ fn jump_if_false(parameters : &Parameters, content : &mut Vec<i32>, id : &mut us
 }
 
 fn less_than(parameters : &Parameters, content : &mut Vec<i32>, id : &mut usize) -> Result<(), &'static str> {
-  let result_id = content[*id + 3] as usize;
+  let result_id = get_address(content, &parameters.three, content[*id + 3])?;
   let value_left = get_value(&content, &parameters.one, content[*id + 1])?;
   let value_right = get_value(&content, &parameters.two, content[*id + 2])?;
-  if result_id >= content.len() {
-    return Err("result_id out of range");
-  }
   content[result_id] = (value_left < value_right) as i32;
   *id += 4;
   Ok(())
 }
 
 fn equals(parameters : &Parameters, content : &mut Vec<i32>, id : &mut usize) -> Result<(), &'static str> {
-  let result_id = content[*id + 3] as usize;
+  let result_id = get_address(content, &parameters.three, content[*id + 3])?;
   let value_left = get_value(&content, &parameters.one, content[*id + 1])?;
   let value_right = get_value(&content, &parameters.two, content[*id + 2])?;
-  if result_id >= content.len() {
-    return Err("result_id out of range");
-  }
   content[result_id] = (value_left == value_right) as i32;
   *id += 4;
   Ok(())
@@ -134,6 +137,7 @@ fn get_method(id : i32) -> Result<(i32, Parameters), &'static str> {
     Parameters {
       one: get_mode(id / 100 % 10)?,
       two: get_mode(id / 1000 % 10)?,
+      three: get_mode(id / 10000 % 10)?,
     }
   ))
 }
@@ -146,8 +150,8 @@ fn calculate(content : &mut Vec<i32>, input : i32) -> Result<i32, &'static str>
     match op_code {
       1 => add(&parameters, content, &mut id)?,
       2 => mult(&parameters, content, &mut id)?,
-      3 => store_input(content, input, &mut id),
-      4 => store_output(content, &mut id, &mut output),
+      3 => store_input(&parameters, content, input, &mut id)?,
+      4 => store_output(&parameters, content, &mut id, &mut output)?,
       5 => jump_if_true(&parameters, content, &mut id)?,
       6 => jump_if_false(&parameters, content, &mut id)?,
       7 => less_than(&parameters, content, &mut id)?,
@@ -174,3 +178,25 @@ fn main() {
   let result2 = calculate(&mut content_copy, 5).expect("Failed to calculate");
   println!("Part2: output: {}", result2);
 }
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use common::intcode_conformance::{run_suite, Execution};
+
+  // This VM feeds the same input to every read and only reports its last output.
+  fn execute(program : &[i64], inputs : &[i64]) -> Result<Execution, &'static str> {
+    let mut content : Vec<i32> = program.iter().map(|&n| n as i32).collect();
+    let output = calculate(&mut content, inputs.first().cloned().unwrap_or(0) as i32)?;
+    Ok(Execution {
+      outputs : vec![output as i64],
+      memory : content.iter().map(|&n| n as i64).collect(),
+    })
+  }
+
+  #[test]
+  fn conformance() {
+    let failures = run_suite(&[], execute);
+    assert!(failures.is_empty(), "{}", failures.join("\n"));
+  }
+}
@@ -0,0 +1,49 @@
+use crate::tree::OrbitTree;
+use crate::{get_orbits_to_santa, get_total_orbits, parse_orbits};
+use common::bench::time;
+
+#[derive(Clone, Copy, Debug)]
+pub enum Shape {
+  // Every object orbits the previous one, SAN hanging from the middle.
+  Chain,
+  // Every object orbits COM, YOU and SAN around the first and last.
+  Fan,
+}
+
+/*
+ * Orbit map of `objects` objects besides COM, YOU and SAN, as the lines of
+ * a puzzle input.
+ */
+pub fn generate_orbits(shape : Shape, objects : usize) -> Vec<String> {
+  let name = |i : usize| if i == 0 { String::from("COM") } else { format!("O{}", i) };
+  let mut lines : Vec<String> = (1..=objects)
+    .map(|i| match shape {
+      Shape::Chain => format!("{}){}", name(i - 1), name(i)),
+      Shape::Fan => format!("COM){}", name(i)),
+    })
+    .collect();
+  let (you, san) = match shape {
+    Shape::Chain => (objects, objects / 2),
+    Shape::Fan => (1.min(objects), objects),
+  };
+  lines.push(format!("{})YOU", name(you)));
+  lines.push(format!("{})SAN", name(san)));
+  lines
+}
+
+// Times every step of both parts on each pathological shape.
+pub fn run(objects : usize) {
+  for shape in [Shape::Chain, Shape::Fan].iter() {
+    println!("Bench: {:?} of {} objects", shape, objects);
+    let lines = generate_orbits(*shape, objects);
+    let (space_map, parse_time) = time(|| parse_orbits(lines.iter()).expect("Invalid generated orbits"));
+    println!("Bench: parse in {:.2?}", parse_time);
+    let root = &space_map.data[space_map.root].name;
+    let (total, total_time) = time(|| get_total_orbits(&space_map, root, 0));
+    println!("Bench: {} orbits in {:.2?}", total, total_time);
+    let (tree, tree_time) = time(|| OrbitTree::new(&space_map));
+    println!("Bench: tree in {:.2?}", tree_time);
+    let (transfers, transfers_time) = time(|| get_orbits_to_santa(&space_map, &tree).unwrap());
+    println!("Bench: {} transfers in {:.2?}", transfers, transfers_time);
+  }
+}
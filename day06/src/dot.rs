@@ -0,0 +1,63 @@
+use crate::{SpaceMap, SpaceObjectId};
+use std::fmt::Write;
+
+const HIGHLIGHT : &str = "crimson";
+
+fn get_quoted(name : &str) -> String {
+  format!("\"{}\"", name.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+/*
+ * Graphviz digraph of the map, every object pointing to those orbiting it.
+ * The objects of `path` and the orbits between them are drawn in color.
+ */
+pub fn get_dot(space_map : &SpaceMap, path : &[SpaceObjectId]) -> String {
+  let mut highlighted = vec![false; space_map.data.len()];
+  path.iter().for_each(|id| highlighted[*id] = true);
+  let mut dot = String::from("digraph orbits {\n  rankdir=LR;\n  node [shape=circle];\n");
+  for id in path {
+    writeln!(dot, "  {} [color={}, fontcolor={}];", get_quoted(&space_map.data[*id].name), HIGHLIGHT, HIGHLIGHT).unwrap();
+  }
+  for (child_id, object) in space_map.data.iter().enumerate() {
+    if let Some(parent_id) = object.parent {
+      let parent = &space_map.data[parent_id];
+      write!(dot, "  {} -> {}", get_quoted(&parent.name), get_quoted(&object.name)).unwrap();
+      if highlighted[parent_id] && highlighted[child_id] {
+        write!(dot, " [color={}, penwidth=2]", HIGHLIGHT).unwrap();
+      }
+      dot += ";\n";
+    }
+  }
+  dot += "}\n";
+  dot
+}
+
+pub fn write_dot(space_map : &SpaceMap, path : &[SpaceObjectId], filename : &str) -> Result<(), String> {
+  std::fs::write(filename, get_dot(space_map, path)).map_err(|e| format!("Could not write {}: {}", filename, e))
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::parse_orbits;
+
+  #[test]
+  fn highlights_path() {
+    let space_map = parse_orbits("COM)B\nB)YOU\nB)\"SAN\"\nCOM)C".lines()).unwrap();
+    let path = [space_map.map["YOU"], space_map.map["B"], space_map.map["\"SAN\""]];
+    let dot = get_dot(&space_map, &path);
+    assert_eq!(dot.lines().collect::<Vec<&str>>(), [
+      "digraph orbits {",
+      "  rankdir=LR;",
+      "  node [shape=circle];",
+      "  \"YOU\" [color=crimson, fontcolor=crimson];",
+      "  \"B\" [color=crimson, fontcolor=crimson];",
+      "  \"\\\"SAN\\\"\" [color=crimson, fontcolor=crimson];",
+      "  \"COM\" -> \"B\";",
+      "  \"B\" -> \"YOU\" [color=crimson, penwidth=2];",
+      "  \"B\" -> \"\\\"SAN\\\"\" [color=crimson, penwidth=2];",
+      "  \"COM\" -> \"C\";",
+      "}",
+    ]);
+  }
+}
@@ -1,5 +1,10 @@
+mod bench;
+mod dot;
+mod query;
+mod tree;
+
 use std::collections::HashMap;
-use std::collections::HashSet;
+use tree::OrbitTree;
 
 type SpaceObjectId = usize;
 
@@ -12,116 +17,348 @@ struct SpaceObject {
 struct SpaceMap {
   data: Vec<SpaceObject>,
   map: HashMap<String, SpaceObjectId>,
+  root : SpaceObjectId,
+}
+
+#[derive(Debug, PartialEq)]
+enum ParseError {
+  InvalidOrbit { line : usize, text : String },
+  DuplicateParent { line : usize, name : String, first_line : usize },
+  Cycle { line : usize, names : Vec<String> },
+  Disconnected { roots : Vec<(String, usize)> },
+  NoOrbits,
 }
 
-fn add_orbit(dic : &mut HashMap<String, Vec<String>>, line: &str) {
-  let parent : String = line.chars().take(3).collect();
-  let child  : String = line.chars().skip(4).take(3).collect();
-  let current_childs = dic.entry(parent.clone()).or_insert(Vec::new());
-  current_childs.push(child);
+impl std::fmt::Display for ParseError {
+  fn fmt(&self, f : &mut std::fmt::Formatter) -> std::fmt::Result {
+    match self {
+      ParseError::InvalidOrbit { line, text } => {
+        write!(f, "line {}: invalid orbit '{}', expected PARENT)CHILD", line, text)
+      },
+      ParseError::DuplicateParent { line, name, first_line } => {
+        write!(f, "line {}: {} already orbits an object since line {}", line, name, first_line)
+      },
+      ParseError::Cycle { line, names } => write!(f, "line {}: orbit cycle {}", line, names.join(")")),
+      ParseError::Disconnected { roots } => {
+        let roots : Vec<String> = roots.iter().map(|(name, line)| format!("{} (line {})", name, line)).collect();
+        write!(f, "several centers of mass: {}", roots.join(", "))
+      },
+      ParseError::NoOrbits => write!(f, "no orbits"),
+    }
+  }
 }
 
-fn dic_to_space_map(dic : &HashMap<String, Vec<String>>, parent : Option<SpaceObjectId>, space_map : &mut SpaceMap, key : String) {
-  let space_objet_id = space_map.data.len();
-  space_map.map.insert(key.clone(), space_objet_id);
+fn get_or_add_object(space_map : &mut SpaceMap, name : &str) -> SpaceObjectId {
+  if let Some(id) = space_map.map.get(name) {
+    return *id;
+  }
+  let id = space_map.data.len();
+  space_map.map.insert(String::from(name), id);
   space_map.data.push(SpaceObject {
-    name : key.clone(),
-    parent: parent,
-    childs: Vec::new(),
+    name : String::from(name),
+    parent : None,
+    childs : Vec::new(),
   });
-  if dic.contains_key(&key) {
-    dic[&key].iter().for_each(|child_name| dic_to_space_map(dic, Some(space_objet_id), space_map, child_name.clone()));
+  id
+}
+
+/*
+ * Objects that nothing reaches from a root: as every object orbits at most
+ * one other, going up from any of them ends in a cycle.
+ */
+fn find_cycle(space_map : &SpaceMap, roots : &[SpaceObjectId], orbit_lines : &[usize]) -> Option<ParseError> {
+  let mut reached = vec![false; space_map.data.len()];
+  let mut stack = roots.to_vec();
+  while let Some(id) = stack.pop() {
+    reached[id] = true;
+    stack.extend(&space_map.data[id].childs);
   }
-  match parent {
-    None => (),
-    Some(id) => space_map.data[id].childs.push(space_objet_id),
+  let mut id = reached.iter().position(|reached| !reached)?;
+  let mut seen = vec![false; space_map.data.len()];
+  while !seen[id] {
+    seen[id] = true;
+    id = space_map.data[id].parent.unwrap();
   }
+  let mut cycle = vec![id];
+  let mut parent_id = space_map.data[id].parent.unwrap();
+  while parent_id != id {
+    cycle.push(parent_id);
+    parent_id = space_map.data[parent_id].parent.unwrap();
+  }
+  cycle.reverse();
+  // The cycle is reported at the line closing it, the last of its orbits.
+  let last = (0..cycle.len()).max_by_key(|i| orbit_lines[cycle[*i]]).unwrap();
+  cycle.rotate_left(last);
+  let mut names : Vec<String> = cycle.iter().map(|id| space_map.data[*id].name.clone()).collect();
+  names.push(names[0].clone());
+  Some(ParseError::Cycle { line : orbit_lines[cycle[0]], names })
 }
 
-fn parse_file(filename : impl AsRef<std::path::Path>) -> SpaceMap {
-  use std::io::{prelude::*};
-  let mut dic = std::collections::HashMap::new();
-  let file = std::fs::File::open(filename).expect("File not found");
-  let buf = std::io::BufReader::new(file);
-  buf.lines()
-    .map(|l| l.expect("Could not parse line"))
-    .for_each(|l| add_orbit(&mut dic, &l));
+/*
+ * One `PARENT)CHILD` orbit per line, names of any length, blank lines being
+ * skipped. The center of mass is the only object orbiting nothing.
+ */
+fn parse_orbits<S : AsRef<str>>(lines : impl Iterator<Item = S>) -> Result<SpaceMap, ParseError> {
   let mut space_map = SpaceMap {
     data : Vec::new(),
     map : HashMap::new(),
+    root : 0,
   };
-  dic_to_space_map(&dic, None, &mut space_map, String::from("COM"));
-  space_map
+  // Line where each object is said to orbit its parent, or first appears.
+  let mut orbit_lines = Vec::new();
+  for (i, line) in lines.enumerate() {
+    let text = line.as_ref().trim();
+    if text.is_empty() {
+      continue;
+    }
+    let names = text.split(')').map(|name| name.trim()).collect::<Vec<&str>>();
+    if names.len() != 2 || names.iter().any(|name| name.is_empty()) {
+      return Err(ParseError::InvalidOrbit { line : i + 1, text : String::from(text) });
+    }
+    let parent_id = get_or_add_object(&mut space_map, names[0]);
+    let child_id = get_or_add_object(&mut space_map, names[1]);
+    orbit_lines.resize(space_map.data.len(), i + 1);
+    if space_map.data[child_id].parent.is_some() {
+      let name = String::from(names[1]);
+      return Err(ParseError::DuplicateParent { line : i + 1, name, first_line : orbit_lines[child_id] });
+    }
+    orbit_lines[child_id] = i + 1;
+    space_map.data[child_id].parent = Some(parent_id);
+    space_map.data[parent_id].childs.push(child_id);
+  }
+  let roots : Vec<SpaceObjectId> = (0..space_map.data.len()).filter(|id| space_map.data[*id].parent.is_none()).collect();
+  if let Some(cycle) = find_cycle(&space_map, &roots, &orbit_lines) {
+    return Err(cycle);
+  }
+  match roots[..] {
+    [] => Err(ParseError::NoOrbits),
+    [root] => {
+      space_map.root = root;
+      Ok(space_map)
+    },
+    _ => Err(ParseError::Disconnected {
+      roots : roots.iter().map(|id| (space_map.data[*id].name.clone(), orbit_lines[*id])).collect(),
+    }),
+  }
 }
 
-fn get_total_orbits(space_map: &SpaceMap, planet_name: &String, distance : usize) -> usize {
-  if !space_map.map.contains_key(planet_name) {
-    distance
-  } else {
-    let mut child_orbits = 0;
-    let object_id = &space_map.map[&String::from(planet_name)];
-    let childs_id = &space_map.data[*object_id].childs;
-    childs_id.iter().for_each(|child_id| child_orbits +=
-      get_total_orbits(space_map, &space_map.data[*child_id].name, distance + 1));
-    child_orbits + distance
+fn parse_file(filename : impl AsRef<std::path::Path>) -> Result<SpaceMap, ParseError> {
+  use std::io::{prelude::*};
+  let file = std::fs::File::open(filename).expect("File not found");
+  let buf = std::io::BufReader::new(file);
+  parse_orbits(buf.lines().map(|l| l.expect("Could not parse line")))
+}
+
+/*
+ * Orbits of the object and of everything around it, the object itself being
+ * `distance` orbits away from the center. Walks the tree with a stack so that
+ * long chains of orbits cannot overflow the call stack.
+ */
+fn get_total_orbits(space_map: &SpaceMap, planet_name: &str, distance : usize) -> usize {
+  let object_id = match space_map.map.get(planet_name) {
+    Some(id) => *id,
+    None => return distance,
+  };
+  let mut total = 0;
+  let mut stack = vec![(object_id, distance)];
+  while let Some((id, distance)) = stack.pop() {
+    total += distance;
+    stack.extend(space_map.data[id].childs.iter().map(|child_id| (*child_id, distance + 1)));
   }
+  total
 }
 
 fn part1(space_map: &SpaceMap) {
   let before = std::time::Instant::now();
-  let result1 = get_total_orbits(space_map, &String::from("COM"), 0);
+  let result1 = get_total_orbits(space_map, &space_map.data[space_map.root].name, 0);
   println!("Elapsed time: {:.2?}", before.elapsed());
   println!("Part1: total number of direct and indirect orbits: {}", result1);
 }
 
-fn get_object_to_visite(space_object : &SpaceObject, visited_node : &HashSet<SpaceObjectId>) -> HashSet<SpaceObjectId> {
-  let mut to_visit : HashSet<SpaceObjectId> = space_object.childs.iter()
-    .filter(|id| !visited_node.contains(&id))
-    .map(|id| *id)
-    .collect();
-    match space_object.parent {
-      None => (),
-      Some(parent_id) => { to_visit.insert(parent_id); () },
-    }
-  to_visit
-}
-
-fn get_orbits_to_santa(space_map: &SpaceMap) -> Result<usize, &'static str> {
-  let you_id = space_map.map["YOU"];
-  let san_id = space_map.map["SAN"];
-  let mut path_len = 0;
-  let mut visited_node = HashSet::new();
-  let mut objects_id_to_visit = get_object_to_visite(&space_map.data[you_id], &visited_node);
-  while objects_id_to_visit.len() > 0 {
-    let mut next_objects_id_to_visit : HashSet<SpaceObjectId> = HashSet::new();
-    for object_id in objects_id_to_visit {
-      if object_id == san_id {
-        return Ok(path_len - 1);
-      }
-      visited_node.insert(object_id);
-      next_objects_id_to_visit.extend(get_object_to_visite(&space_map.data[object_id], &visited_node));
-    }
-    objects_id_to_visit = next_objects_id_to_visit;
-    path_len += 1;
+fn get_id(space_map : &SpaceMap, name : &str) -> Result<SpaceObjectId, String> {
+  space_map.map.get(name).copied().ok_or(format!("unknown object {}", name))
+}
+
+fn get_transfer_path(space_map : &SpaceMap, tree : &OrbitTree) -> Result<Vec<SpaceObjectId>, String> {
+  let you_id = get_id(space_map, "YOU")?;
+  let san_id = get_id(space_map, "SAN")?;
+  tree.get_path(you_id, san_id).ok_or(String::from("no path from YOU to SAN"))
+}
+
+fn get_orbits_to_santa(space_map : &SpaceMap, tree : &OrbitTree) -> Result<usize, String> {
+  match get_transfer_path(space_map, tree)?.len() {
+    // The path holds YOU and SAN, and every transfer reaches the next object.
+    length if length >= 3 => Ok(length - 3),
+    _ => Err(String::from("YOU and SAN orbit each other")),
   }
-  Err("Path not found")
 }
 
-fn part2(space_map: &SpaceMap) {
+fn part2(space_map: &SpaceMap, tree : &OrbitTree) {
   let before = std::time::Instant::now();
-  let result = get_orbits_to_santa(space_map).unwrap();
+  let result = get_orbits_to_santa(space_map, tree).unwrap();
   println!("Elapsed time: {:.2?}", before.elapsed());
   println!("Part2: orbits to reach Santa: {}", result);
 }
 
+// Size of the tree, and how far out its objects orbit.
+fn print_summary(space_map : &SpaceMap, tree : &OrbitTree) {
+  let root = space_map.root;
+  println!("Tree: {} holds {} objects", space_map.data[root].name, tree.get_subtree_size(root));
+  let deepest = tree.get_leaves().iter().max_by_key(|id| tree.get_depth(**id));
+  if let Some(id) = deepest {
+    println!("Tree: {} leaves, {} is the farthest at {} orbits", tree.get_leaves().len(), space_map.data[*id].name, tree.get_depth(*id));
+  }
+}
+
+struct Options {
+  bench : Option<usize>,
+  dot : Option<String>,
+  highlight : bool,
+  // Words of the query to answer, empty to read queries from the input.
+  query : Option<Vec<String>>,
+}
+
+fn parse_options(args : &[String]) -> Result<Options, &'static str> {
+  let mut options = Options {
+    bench : None,
+    dot : None,
+    highlight : false,
+    query : None,
+  };
+  if args.first().map(|arg| arg.as_str()) == Some("query") {
+    options.query = Some(args[1..].to_vec());
+    return Ok(options);
+  }
+  let mut it = args.iter();
+  while let Some(arg) = it.next() {
+    match arg.as_str() {
+      "--dot" => options.dot = Some(it.next().ok_or("Missing value after --dot")?.clone()),
+      "--highlight" => options.highlight = true,
+      "--bench" => {
+        options.bench = Some(it.next().ok_or("Missing value after --bench")?
+          .parse::<usize>().map_err(|_| "Invalid number of objects")?)
+      },
+      _ => return Err("Unknown option"),
+    }
+  }
+  if options.highlight && options.dot.is_none() {
+    return Err("--highlight needs --dot");
+  }
+  Ok(options)
+}
+
 fn main() {
   let args: Vec<String> = std::env::args().collect();
-  if args.len() < 2 {
-    panic!("Not enough arguments");
+  // Benchmarks generate their own maps, so they need no input file.
+  let (filename, options) = match args.get(1) {
+    Some(arg) if arg.starts_with("--") => (None, parse_options(&args[1..])),
+    Some(filename) => (Some(filename), parse_options(&args[2..])),
+    None => panic!("Not enough arguments"),
+  };
+  let options = options.expect("Invalid arguments");
+  if let Some(objects) = options.bench {
+    bench::run(objects);
+    return;
   }
-  let filename = &args[1];
+  let filename = filename.expect("Not enough arguments");
   println!("Loading file {}", filename);
-  let content = parse_file(filename);
+  let content = match parse_file(filename) {
+    Ok(content) => content,
+    Err(e) => panic!("Invalid orbits: {}", e),
+  };
+  let tree = OrbitTree::new(&content);
+  if let Some(words) = options.query {
+    if words.is_empty() {
+      query::run_interactive(&content, &tree);
+    } else {
+      let words : Vec<&str> = words.iter().map(|word| word.as_str()).collect();
+      match query::answer(&content, &tree, &words) {
+        Ok(result) => println!("{}", result),
+        Err(e) => panic!("Invalid query: {}", e),
+      }
+    }
+    return;
+  }
+  // Checked before any answer, so that a map without YOU or SAN fails early.
+  let path = if options.highlight {
+    get_transfer_path(&content, &tree).unwrap_or_else(|e| panic!("Cannot highlight the transfers: {}", e))
+  } else {
+    Vec::new()
+  };
+  print_summary(&content, &tree);
   part1(&content);
-  part2(&content);
+  part2(&content, &tree);
+  if let Some(filename) = options.dot {
+    dot::write_dot(&content, &path, &filename).unwrap();
+    println!("Orbits written to {}", filename);
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use bench::{generate_orbits, Shape};
+
+  fn parse(text : &str) -> Result<SpaceMap, ParseError> {
+    parse_orbits(text.lines())
+  }
+
+  #[test]
+  fn parses_names_of_any_length() {
+    let space_map = parse("B)Andromeda\n\n  Sun ) B\nAndromeda)YOU\nB)SAN\n").unwrap();
+    assert_eq!(space_map.data[space_map.root].name, "Sun");
+    let tree = OrbitTree::new(&space_map);
+    assert_eq!(get_total_orbits(&space_map, "Sun", 0), 1 + 2 + 3 + 2);
+    assert_eq!(get_orbits_to_santa(&space_map, &tree), Ok(1));
+  }
+
+  #[test]
+  fn reports_invalid_maps() {
+    assert_eq!(parse("COM)B\nB-C").err(), Some(ParseError::InvalidOrbit { line : 2, text : String::from("B-C") }));
+    assert_eq!(parse("COM)B\nB)C)D").err(), Some(ParseError::InvalidOrbit { line : 2, text : String::from("B)C)D") }));
+    assert_eq!(parse(")B").err(), Some(ParseError::InvalidOrbit { line : 1, text : String::from(")B") }));
+    assert_eq!(
+      parse("COM)B\nB)C\nCOM)C").err(),
+      Some(ParseError::DuplicateParent { line : 3, name : String::from("C"), first_line : 2 })
+    );
+    let names = |names : &[&str]| names.iter().map(|name| String::from(*name)).collect();
+    assert_eq!(
+      parse("COM)B\nX)Y\nY)Z\nZ)X").err(),
+      Some(ParseError::Cycle { line : 4, names : names(&["X", "Y", "Z", "X"]) })
+    );
+    assert_eq!(parse("A)A").err(), Some(ParseError::Cycle { line : 1, names : names(&["A", "A"]) }));
+    assert_eq!(
+      parse("COM)B\nB)C\nX)Y").err(),
+      Some(ParseError::Disconnected { roots : vec![(String::from("COM"), 1), (String::from("X"), 3)] })
+    );
+    assert_eq!(parse("\n\n").err(), Some(ParseError::NoOrbits));
+  }
+
+  #[test]
+  fn validates_options() {
+    let parse = |args : &[&str]| {
+      parse_options(&args.iter().map(|arg| String::from(*arg)).collect::<Vec<String>>()).map(|options| options.dot)
+    };
+    assert_eq!(parse(&["--highlight"]), Err("--highlight needs --dot"));
+    assert_eq!(parse(&["--highlight", "--dot", "orbits.dot"]), Ok(Some(String::from("orbits.dot"))));
+    let space_map = parse_orbits("COM)B\nB)YOU".lines()).unwrap();
+    let tree = OrbitTree::new(&space_map);
+    assert_eq!(get_transfer_path(&space_map, &tree), Err(String::from("unknown object SAN")));
+  }
+
+  #[test]
+  fn survives_deep_and_wide_maps() {
+    let objects = 300_000;
+    let space_map = parse_orbits(generate_orbits(Shape::Chain, objects).iter()).unwrap();
+    let tree = OrbitTree::new(&space_map);
+    let chain = objects * (objects + 1) / 2;
+    assert_eq!(get_total_orbits(&space_map, "COM", 0), chain + (objects + 1) + (objects / 2 + 1));
+    assert_eq!(get_orbits_to_santa(&space_map, &tree), Ok(objects / 2));
+    assert_eq!(tree.get_subtree_size(space_map.map["O1"]), objects + 2);
+    let space_map = parse_orbits(generate_orbits(Shape::Fan, objects).iter()).unwrap();
+    let tree = OrbitTree::new(&space_map);
+    assert_eq!(get_total_orbits(&space_map, "COM", 0), objects + 2 * 2);
+    assert_eq!(get_orbits_to_santa(&space_map, &tree), Ok(2));
+    assert_eq!(tree.get_leaves().len(), objects);
+  }
 }
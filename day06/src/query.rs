@@ -0,0 +1,78 @@
+use crate::tree::OrbitTree;
+use crate::{get_id, SpaceMap, SpaceObjectId};
+
+pub const USAGE : &str = "path A B | depth X | ancestors X | lca A B | size X | leaves";
+
+fn get_names(space_map : &SpaceMap, ids : impl Iterator<Item = SpaceObjectId>) -> String {
+  ids.map(|id| space_map.data[id].name.as_str()).collect::<Vec<&str>>().join(" ")
+}
+
+/*
+ * Answer to one query, its words being the query name then the names of the
+ * objects it is about.
+ */
+pub fn answer(space_map : &SpaceMap, tree : &OrbitTree, words : &[&str]) -> Result<String, String> {
+  let id = |i : usize| get_id(space_map, words[i]);
+  match (words.first().copied(), words.len()) {
+    (Some("path"), 3) => {
+      let path = tree.get_path(id(1)?, id(2)?).ok_or("no path")?;
+      Ok(format!("{} ({} hops)", get_names(space_map, path.iter().copied()), path.len() - 1))
+    },
+    (Some("depth"), 2) => Ok(tree.get_depth(id(1)?).to_string()),
+    (Some("ancestors"), 2) => {
+      let mut ancestors = Vec::new();
+      let mut parent = space_map.data[id(1)?].parent;
+      while let Some(parent_id) = parent {
+        ancestors.push(parent_id);
+        parent = space_map.data[parent_id].parent;
+      }
+      Ok(get_names(space_map, ancestors.into_iter()))
+    },
+    (Some("lca"), 3) => {
+      let lca = tree.get_lca(id(1)?, id(2)?).ok_or("no common ancestor")?;
+      Ok(space_map.data[lca].name.clone())
+    },
+    (Some("size"), 2) => Ok(tree.get_subtree_size(id(1)?).to_string()),
+    (Some("leaves"), 1) => Ok(get_names(space_map, tree.get_leaves().iter().copied())),
+    _ => Err(format!("expected {}", USAGE)),
+  }
+}
+
+// Answers the queries read from the standard input, one per line.
+pub fn run_interactive(space_map : &SpaceMap, tree : &OrbitTree) {
+  use std::io::{prelude::*};
+  println!("Queries: {}", USAGE);
+  for line in std::io::stdin().lock().lines() {
+    let line = line.expect("Could not read query");
+    let words : Vec<&str> = line.split_whitespace().collect();
+    if words.is_empty() {
+      continue;
+    }
+    match answer(space_map, tree, &words) {
+      Ok(result) => println!("{}", result),
+      Err(e) => println!("Error: {}", e),
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::parse_orbits;
+
+  #[test]
+  fn answers_queries() {
+    let space_map = parse_orbits("COM)B\nB)C\nC)D\nD)E\nE)F\nB)G\nG)H\nD)I\nE)J\nJ)K\nK)L\nK)YOU\nI)SAN".lines()).unwrap();
+    let tree = OrbitTree::new(&space_map);
+    let query = |text : &str| answer(&space_map, &tree, &text.split_whitespace().collect::<Vec<&str>>());
+    assert_eq!(query("path YOU SAN"), Ok(String::from("YOU K J E D I SAN (6 hops)")));
+    assert_eq!(query("depth L"), Ok(String::from("7")));
+    assert_eq!(query("ancestors I"), Ok(String::from("D C B COM")));
+    assert_eq!(query("ancestors COM"), Ok(String::new()));
+    assert_eq!(query("lca H SAN"), Ok(String::from("B")));
+    assert_eq!(query("size D"), Ok(String::from("9")));
+    assert_eq!(query("leaves"), Ok(String::from("F L YOU SAN H")));
+    assert_eq!(query("depth X"), Err(String::from("unknown object X")));
+    assert_eq!(query("depth"), Err(format!("expected {}", USAGE)));
+  }
+}
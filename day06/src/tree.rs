@@ -0,0 +1,145 @@
+use crate::{SpaceMap, SpaceObjectId};
+
+/*
+ * Queries on the orbit tree once it has been preprocessed: every object gets
+ * its depth, its 2^k-th ancestors (binary lifting) and the interval of its
+ * subtree in a preorder walk (Euler tour). The map may hold several trees,
+ * objects of different trees having no common ancestor.
+ */
+pub struct OrbitTree {
+  depth : Vec<usize>,
+  root : Vec<SpaceObjectId>,
+  up : Vec<Vec<SpaceObjectId>>,
+  enter : Vec<usize>,
+  exit : Vec<usize>,
+  leaves : Vec<SpaceObjectId>,
+}
+
+impl OrbitTree {
+  pub fn new(space_map : &SpaceMap) -> OrbitTree {
+    let size = space_map.data.len();
+    let mut tree = OrbitTree {
+      depth : vec![0; size],
+      root : (0..size).collect(),
+      up : vec![(0..size).collect()],
+      enter : vec![0; size],
+      exit : vec![0; size],
+      leaves : Vec::new(),
+    };
+    let mut order = 0;
+    for root_id in (0..size).filter(|id| space_map.data[*id].parent.is_none()) {
+      // An object is pushed twice: once to enter it, once to leave it.
+      let mut stack = vec![(root_id, false)];
+      while let Some((id, leaving)) = stack.pop() {
+        if leaving {
+          tree.exit[id] = order;
+          continue;
+        }
+        let object = &space_map.data[id];
+        if let Some(parent_id) = object.parent {
+          tree.depth[id] = tree.depth[parent_id] + 1;
+          tree.root[id] = tree.root[parent_id];
+          tree.up[0][id] = parent_id;
+        }
+        if object.childs.is_empty() {
+          tree.leaves.push(id);
+        }
+        tree.enter[id] = order;
+        order += 1;
+        stack.push((id, true));
+        stack.extend(object.childs.iter().rev().map(|child_id| (*child_id, false)));
+      }
+    }
+    let max_depth = tree.depth.iter().copied().max().unwrap_or(0);
+    while 1 << tree.up.len() <= max_depth {
+      let last = tree.up.last().unwrap();
+      let next = last.iter().map(|id| last[*id]).collect();
+      tree.up.push(next);
+    }
+    tree
+  }
+
+  // Number of direct and indirect orbits of the object.
+  pub fn get_depth(&self, id : SpaceObjectId) -> usize {
+    self.depth[id]
+  }
+
+  // Number of objects orbiting around the object, itself included.
+  pub fn get_subtree_size(&self, id : SpaceObjectId) -> usize {
+    self.exit[id] - self.enter[id]
+  }
+
+  // Objects nothing orbits around, in preorder.
+  pub fn get_leaves(&self) -> &[SpaceObjectId] {
+    &self.leaves
+  }
+
+  pub fn is_ancestor(&self, ancestor : SpaceObjectId, id : SpaceObjectId) -> bool {
+    self.enter[ancestor] <= self.enter[id] && self.exit[id] <= self.exit[ancestor]
+  }
+
+  pub fn get_lca(&self, id1 : SpaceObjectId, id2 : SpaceObjectId) -> Option<SpaceObjectId> {
+    if self.root[id1] != self.root[id2] {
+      return None;
+    }
+    if self.is_ancestor(id1, id2) {
+      return Some(id1);
+    }
+    let mut id = id1;
+    for up in self.up.iter().rev() {
+      if !self.is_ancestor(up[id], id2) {
+        id = up[id];
+      }
+    }
+    Some(self.up[0][id])
+  }
+
+  // Objects from the first to the second, both included.
+  pub fn get_path(&self, id1 : SpaceObjectId, id2 : SpaceObjectId) -> Option<Vec<SpaceObjectId>> {
+    let lca = self.get_lca(id1, id2)?;
+    let climb = |mut id : SpaceObjectId| {
+      let mut path = Vec::new();
+      while id != lca {
+        path.push(id);
+        id = self.up[0][id];
+      }
+      path
+    };
+    let mut path = climb(id1);
+    path.push(lca);
+    path.extend(climb(id2).iter().rev());
+    Some(path)
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::parse_orbits;
+
+  const EXAMPLE : &str = "COM)BBB\nBBB)CCC\nCCC)DDD\nDDD)EEE\nEEE)FFF\nBBB)GGG\nGGG)HHH\nDDD)III\nEEE)JJJ\nJJJ)KKK\nKKK)LLL\nKKK)YOU\nIII)SAN";
+
+  fn get_names(space_map : &SpaceMap, ids : &[SpaceObjectId]) -> Vec<String> {
+    ids.iter().map(|id| space_map.data[*id].name.clone()).collect()
+  }
+
+  #[test]
+  fn answers_tree_queries() {
+    let space_map = parse_orbits(EXAMPLE.lines()).unwrap();
+    let tree = OrbitTree::new(&space_map);
+    let id = |name : &str| space_map.map[name];
+    assert_eq!((0..space_map.data.len()).map(|id| tree.get_depth(id)).sum::<usize>(), 54);
+    assert_eq!(tree.get_depth(id("LLL")), 7);
+    assert_eq!(tree.get_subtree_size(id("COM")), 14);
+    assert_eq!(tree.get_subtree_size(id("EEE")), 6);
+    assert_eq!(tree.get_subtree_size(id("YOU")), 1);
+    assert_eq!(get_names(&space_map, tree.get_leaves()), ["FFF", "LLL", "YOU", "SAN", "HHH"]);
+    assert_eq!(tree.get_lca(id("YOU"), id("SAN")), Some(id("DDD")));
+    assert_eq!(tree.get_lca(id("HHH"), id("COM")), Some(id("COM")));
+    assert_eq!(tree.get_lca(id("FFF"), id("FFF")), Some(id("FFF")));
+    let path = tree.get_path(id("YOU"), id("SAN")).unwrap();
+    assert_eq!(get_names(&space_map, &path), ["YOU", "KKK", "JJJ", "EEE", "DDD", "III", "SAN"]);
+    let path = tree.get_path(id("COM"), id("LLL")).unwrap();
+    assert_eq!(get_names(&space_map, &path), ["COM", "BBB", "CCC", "DDD", "EEE", "JJJ", "KKK", "LLL"]);
+  }
+}
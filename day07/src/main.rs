@@ -1,285 +1,187 @@
+use common::vm;
 use std::{
   env,
-  fs,
-  io::{prelude::*},
-  path,
-  collections::VecDeque,
-  collections::HashSet,
+  ops::RangeInclusive,
 };
 
-
-fn parse_file(filename : impl AsRef<path::Path>) -> Vec<i32> {
-  let mut file = fs::File::open(filename).expect("File not found");
-  let mut contents = String::new();
-  file.read_to_string(&mut contents).expect("Could not read file");
-  contents.trim().split(",")
-    .map(|l| l.parse::<i32>().expect("Could not parse number"))
-    .collect()
+#[derive(Clone, Copy)]
+enum Topology {
+  Serial,
+  Feedback,
 }
 
-enum Mode {
-  Position = 0,
-  Immediate = 1,
+struct Settings {
+  amplifiers : usize,
+  phases : RangeInclusive<vm::BigByte>,
+  topology : Topology,
 }
 
-type Parameters = [Mode; 2];
+struct Best {
+  phases : Vec<vm::BigByte>,
+  output : vm::BigByte,
+}
 
-struct VirtualMachine {
-  memory : Vec<i32>,
-  pointer : usize,
-  inputs : VecDeque<i32>,
-  halted : bool,
+fn get_topology(text : &str) -> Result<Topology, &'static str> {
+  match text {
+    "serial" => Ok(Topology::Serial),
+    "feedback" => Ok(Topology::Feedback),
+    _ => Err("Invalid mode, expected serial or feedback"),
+  }
 }
 
-fn get_mode(value : i32) -> Result<Mode, &'static str> {
-  match value {
-    0 => Ok(Mode::Position),
-    1 => Ok(Mode::Immediate),
-    _ => Err("Invalid value")
+fn get_phases(text : &str) -> Result<RangeInclusive<vm::BigByte>, &'static str> {
+  let mut it = text.splitn(2, '-');
+  let first = it.next()
+    .and_then(|n| n.parse::<vm::BigByte>().ok())
+    .ok_or("Invalid phase range, expected <first>-<last>")?;
+  let last = it.next()
+    .and_then(|n| n.parse::<vm::BigByte>().ok())
+    .ok_or("Invalid phase range, expected <first>-<last>")?;
+  if first > last {
+    return Err("Invalid phase range, first phase is greater than the last one");
   }
+  Ok(first..=last)
 }
 
-impl VirtualMachine {
-  fn get_value(&self, parameters : &Parameters, arg_id : usize) -> Result<i32, &'static str> {
-    let arg = self.memory[self.pointer + arg_id];
-    match parameters[arg_id - 1] {
-      Mode::Position => {
-        if arg < 0 {
-          return Err("Negative parameter is in position mode");
-        } else if (arg as usize) >= self.memory.len() {
-          return Err("Parameter is in position mode and out of range");
-        }
-        Ok(self.memory[arg as usize])
+fn parse_settings(args : &[String]) -> Result<Settings, &'static str> {
+  let mut settings = Settings {
+    amplifiers : 5,
+    phases : 0..=4,
+    topology : Topology::Serial,
+  };
+  let mut phases_given = false;
+  let mut it = args.iter();
+  while let Some(arg) = it.next() {
+    let value = it.next().ok_or("Missing value after option")?;
+    match arg.as_str() {
+      "--amplifiers" => {
+        settings.amplifiers = value.parse::<usize>().map_err(|_| "Invalid number of amplifiers")?
+      },
+      "--phases" => {
+        settings.phases = get_phases(value)?;
+        phases_given = true;
       },
-      Mode::Immediate => Ok(arg),
+      "--mode" => settings.topology = get_topology(value)?,
+      _ => return Err("Unknown option, expected --amplifiers, --phases or --mode"),
     }
   }
-
-  fn add(&mut self, parameters : &Parameters) -> Result<(), &'static str> {
-    let result_id = self.memory[self.pointer + 3] as usize;
-    let value_left = self.get_value(&parameters, 1)?;
-    let value_right = self.get_value(&parameters, 2)?;
-    if result_id >= self.memory.len() {
-      return Err("result_id out of range");
+  if !phases_given {
+    if let Topology::Feedback = settings.topology {
+      settings.phases = 5..=9;
     }
-    self.memory[result_id] = value_left + value_right;
-    self.pointer += 4;
-    Ok(())
   }
-
-  fn mult(&mut self, parameters : &Parameters) -> Result<(), &'static str> {
-    let result_id = self.memory[self.pointer + 3] as usize;
-    let value_left = self.get_value(&parameters, 1)?;
-    let value_right = self.get_value(&parameters, 2)?;
-    if result_id >= self.memory.len() {
-      return Err("result_id out of range");
-    }
-    self.memory[result_id] = value_left * value_right;
-    self.pointer += 4;
-    Ok(())
+  if settings.amplifiers == 0 {
+    return Err("At least one amplifier is required");
   }
-
-  fn store_input(&mut self) -> Result<(), &'static str> {
-    match self.inputs.front() {
-      Some(input) => {
-        let ptr = self.memory[self.pointer + 1] as usize;
-        self.memory[ptr] = *input;
-        self.pointer += 2;
-        self.inputs.pop_front();
-        Ok(())
-      },
-      None => Err("No input found"),
-    }
-  }
-
-  fn store_output(&mut self, output : &mut i32) {
-    let output_ptr = self.memory[self.pointer + 1] as usize;
-    *output = self.memory[output_ptr];
-    self.pointer += 2;
+  if settings.phases.clone().count() < settings.amplifiers {
+    return Err("Not enough phases for every amplifier to get a distinct one");
   }
+  Ok(settings)
+}
 
-  fn jump_if_true(&mut self, parameters : &Parameters) -> Result<(), &'static str> {
-    let value_left = self.get_value(&parameters, 1)?;
-    let value_right = self.get_value(&parameters, 2)?;
-    if value_left != 0 {
-      self.pointer = value_right as usize;
-    } else {
-      self.pointer += 3;
-    }
-    Ok(())
-  }
+type Visitor<'a> = dyn FnMut(&[vm::BigByte]) -> Result<(), &'static str> + 'a;
 
-  fn jump_if_false(&mut self, parameters : &Parameters) -> Result<(), &'static str> {
-    let value_left = self.get_value(&parameters, 1)?;
-    let value_right = self.get_value(&parameters, 2)?;
-    if value_left == 0 {
-      self.pointer = value_right as usize;
-    } else {
-      self.pointer += 3;
+/*
+ * Visits every ordered selection of `length` distinct phases taken from
+ * `phases`, in lexicographic order. Each one is built in place when its turn
+ * comes, so only one is held in memory. The first error stops the visit.
+ */
+fn visit_phase_sequences(phases : &RangeInclusive<vm::BigByte>, length : usize, visit : &mut Visitor)
+  -> Result<(), &'static str>
+{
+  fn complete(available : &mut Vec<vm::BigByte>, current : &mut Vec<vm::BigByte>, length : usize, visit : &mut Visitor)
+    -> Result<(), &'static str>
+  {
+    if current.len() == length {
+      return visit(current);
     }
-    Ok(())
-  }
-
-  fn less_than(&mut self, parameters : &Parameters) -> Result<(), &'static str> {
-    let result_id = self.memory[self.pointer + 3] as usize;
-    let value_left = self.get_value(&parameters, 1)?;
-    let value_right = self.get_value(&parameters, 2)?;
-    if result_id >= self.memory.len() {
-      return Err("result_id out of range");
+    for i in 0..available.len() {
+      let phase = available.remove(i);
+      current.push(phase);
+      complete(available, current, length, visit)?;
+      current.pop();
+      available.insert(i, phase);
     }
-    self.memory[result_id] = (value_left < value_right) as i32;
-    self.pointer += 4;
     Ok(())
   }
+  let mut available : Vec<vm::BigByte> = phases.clone().collect();
+  complete(&mut available, &mut Vec::with_capacity(length), length, visit)
+}
 
-  fn equals(&mut self, parameters : &Parameters) -> Result<(), &'static str> {
-    let result_id = self.memory[self.pointer + 3] as usize;
-    let value_left = self.get_value(&parameters, 1)?;
-    let value_right = self.get_value(&parameters, 2)?;
-    if result_id >= self.memory.len() {
-      return Err("result_id out of range");
-    }
-    self.memory[result_id] = (value_left == value_right) as i32;
-    self.pointer += 4;
-    Ok(())
-  }
+fn create_cluster(memory : &vm::Memory, phases : &[vm::BigByte]) -> Vec<vm::VirtualMachine> {
+  phases.iter()
+    .map(|&phase| {
+      let mut vm = vm::create_vm(memory.clone(), vm::Tier::Interpreter);
+      vm.push_input(phase);
+      vm
+    })
+    .collect()
+}
 
-  fn get_method(&self) -> Result<(i32, Parameters), &'static str> {
-    let id = self.memory[self.pointer];
-    Ok(( id % 100,
-      [ 
-        get_mode(id / 100 % 10)?,
-        get_mode(id / 1000 % 10)?,
-      ]
-    ))
+/*
+ * Runs one amplifier on the given signal and forwards the last value it
+ * printed. Returns None once the amplifier halted without printing anything,
+ * and fails if it waits for more input without having printed anything.
+ */
+fn amplify(vm : &mut vm::VirtualMachine, signal : vm::BigByte) -> Result<Option<vm::BigByte>, &'static str> {
+  vm.push_input(signal);
+  vm.run()?;
+  let mut output = None;
+  while let Some(value) = vm.pop_output() {
+    output = Some(value);
   }
-
-  fn run(&mut self) -> Result<Option<i32>, &'static str> {
-    self.halted = false;
-    let mut output = 0;
-    loop {
-      let ( op_code, parameters ) = self.get_method()?;
-      match op_code {
-        1 => self.add(&parameters)?,
-        2 => self.mult(&parameters)?,
-        3 => self.store_input()?,
-        4 => {
-          self.store_output(&mut output);
-          return Ok(Some(output));
-        },
-        5 => self.jump_if_true(&parameters)?,
-        6 => self.jump_if_false(&parameters)?,
-        7 => self.less_than(&parameters)?,
-        8 => self.equals(&parameters)?,
-        99 => {
-          return Ok(None);
-        },
-        _ => return Err("Invalid instruction"),
-      }
-    }
+  if output.is_none() && vm.is_running() {
+    return Err("Amplifier waits for input without any output");
   }
+  Ok(output)
 }
 
-fn create_vm(memory : &Vec<i32>, inputs : VecDeque<i32>) -> VirtualMachine {
-  VirtualMachine {
-    memory : memory.clone(),
-    pointer : 0,
-    inputs : inputs,
-    halted : true,
+fn run_serial(cluster : &mut [vm::VirtualMachine]) -> Result<vm::BigByte, &'static str> {
+  let mut signal = 0;
+  for vm in cluster {
+    signal = amplify(vm, signal)?.ok_or("Amplifier did not return an output")?;
   }
+  Ok(signal)
 }
 
-fn create_cluster(memory : &Vec<i32>, inputs : &[i32; 5]) -> [VirtualMachine; 5] {
-  [
-    create_vm(memory, vec!(inputs[0]).into_iter().collect()),
-    create_vm(memory, vec!(inputs[1]).into_iter().collect()),
-    create_vm(memory, vec!(inputs[2]).into_iter().collect()),
-    create_vm(memory, vec!(inputs[3]).into_iter().collect()),
-    create_vm(memory, vec!(inputs[4]).into_iter().collect()),
-  ]
-}
-
-/*
- * An absurd inneficient solution. Because looking for a permutation algorithm is too hard.
- */
-fn update_settings(settings : &mut [i32; 5]) -> bool {
-  let mut recycles : HashSet<i32> = HashSet::new();
-  let mut highest_recycle = 0;
-  for i in (0..settings.len()).rev() {
-    if highest_recycle > settings[i] {
-      let mut smallest = highest_recycle;
-      for recycle in &recycles {
-        if *recycle > settings[i] && *recycle < smallest {
-          smallest = *recycle;
-        }
-      }
-      recycles.insert(settings[i]);
-      settings[i] = smallest;
-      recycles.remove(&smallest);
-      let mut sorted_recycles : Vec<i32> = recycles.iter().map(|&x| x).collect();
-      sorted_recycles.sort();
-      for j in 0..sorted_recycles.len() {
-        settings[i+1+j] = sorted_recycles[j];
-      }
-      return true;
-    } else {
-      if settings[i] > highest_recycle {
-        highest_recycle = settings[i];
+fn run_feedback(cluster : &mut [vm::VirtualMachine]) -> Result<vm::BigByte, &'static str> {
+  let mut signal = 0;
+  loop {
+    for vm in cluster.iter_mut() {
+      match amplify(vm, signal)? {
+        Some(result) => signal = result,
+        None => return Ok(signal),
       }
-      recycles.insert(settings[i]);
+    }
+    if cluster.iter_mut().all(|vm| !vm.is_running()) {
+      return Ok(signal);
     }
   }
-  false
 }
 
-fn part1(content : &Vec<i32>) {
-  let before = std::time::Instant::now();
-  let mut settings = [0,1,2,3,4];
-  let mut max_output = 0;
-  while update_settings(&mut settings) {
-    let mut output = 0;
-    let mut cluster = create_cluster(content, &settings);
-    for vm in &mut cluster {
-      vm.inputs.push_back(output);
-      match vm.run().expect("Failed to calculate") {
-        Some(result) => output = result,
-        None => panic!("Program did not return an output"),
-      }
-    }
-    if output > max_output {
-      max_output = output
+fn find_best(memory : &vm::Memory, settings : &Settings) -> Result<Best, &'static str> {
+  let mut best : Option<Best> = None;
+  visit_phase_sequences(&settings.phases, settings.amplifiers, &mut |phases| {
+    let mut cluster = create_cluster(memory, phases);
+    let output = match settings.topology {
+      Topology::Serial => run_serial(&mut cluster)?,
+      Topology::Feedback => run_feedback(&mut cluster)?,
     };
-  }
-  println!("Elapsed time: {:.2?}", before.elapsed());
-  println!("Part1: max output: {}", max_output);
+    if best.as_ref().is_none_or(|b| output > b.output) {
+      best = Some(Best { phases : phases.to_vec(), output });
+    }
+    Ok(())
+  })?;
+  best.ok_or("No phase sequence to try")
 }
 
-fn part2(content : &Vec<i32>) {
+fn print_best(name : &str, memory : &vm::Memory, settings : &Settings) {
   let before = std::time::Instant::now();
-  let mut settings = [5,6,7,8,9];
-  let mut max_output = 0;
-  while update_settings(&mut settings) {
-    let mut output = 0;
-    let mut cluster = create_cluster(content, &settings);
-    let mut all_vm_running = true;
-    while all_vm_running {
-      for vm in &mut cluster {
-        vm.inputs.push_back(output);
-        match vm.run().expect("Failed to calculate") {
-          Some(result) => output = result,
-          None => {
-            all_vm_running = false;
-            break;
-          },
-        }
-    }
-    }
-    if output > max_output {
-      max_output = output
-    };
-  }
+  let best = find_best(memory, settings).expect("Failed to calculate");
+  let phases : Vec<String> = best.phases.iter().map(|p| p.to_string()).collect();
   println!("Elapsed time: {:.2?}", before.elapsed());
-  println!("Part2: max output: {}", max_output);
+  println!("{}: max output: {}", name, best.output);
+  println!("{}: phase sequence: {}", name, phases.join(","));
 }
 
 fn main() {
@@ -289,7 +191,102 @@ fn main() {
   }
   let filename = &args[1];
   println!("Loading file {}", filename);
-  let content = parse_file(filename);
-  part1(&content);
-  part2(&content);
+  let memory = vm::parse_memory_file(filename);
+  if args.len() > 2 {
+    let settings = parse_settings(&args[2..]).expect("Invalid arguments");
+    print_best("Custom", &memory, &settings);
+  } else {
+    print_best("Part1", &memory, &Settings { amplifiers : 5, phases : 0..=4, topology : Topology::Serial });
+    print_best("Part2", &memory, &Settings { amplifiers : 5, phases : 5..=9, topology : Topology::Feedback });
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  const SERIAL : &str = "3,15,3,16,1002,16,10,16,1,16,15,15,4,15,99,0,0";
+  const FEEDBACK : &str = "3,26,1001,26,-4,26,3,27,1002,27,2,27,1,27,26,27,4,27,1001,28,-1,28,1005,28,6,99,0,0,5";
+
+  fn parse_args(args : &str) -> Result<Settings, &'static str> {
+    parse_settings(&args.split_whitespace().map(String::from).collect::<Vec<String>>())
+  }
+
+  #[test]
+  fn parses_phase_ranges() {
+    assert_eq!(get_phases("0-4"), Ok(0..=4));
+    assert_eq!(get_phases("7-7"), Ok(7..=7));
+    assert!(get_phases("4-0").is_err());
+    assert!(get_phases("3").is_err());
+    assert!(get_phases("a-4").is_err());
+  }
+
+  #[test]
+  fn parses_settings() {
+    let settings = parse_args("").unwrap();
+    assert_eq!((settings.amplifiers, settings.phases), (5, 0..=4));
+    let settings = parse_args("--mode feedback").unwrap();
+    assert_eq!((settings.amplifiers, settings.phases), (5, 5..=9));
+    let settings = parse_args("--mode feedback --phases 0-3 --amplifiers 3").unwrap();
+    assert_eq!((settings.amplifiers, settings.phases), (3, 0..=3));
+    assert_eq!(parse_args("--amplifiers 0").err(), Some("At least one amplifier is required"));
+    assert_eq!(parse_args("--amplifiers 6").err(), Some("Not enough phases for every amplifier to get a distinct one"));
+    assert_eq!(parse_args("--mode").err(), Some("Missing value after option"));
+    assert!(parse_args("--mode parallel").is_err());
+    assert!(parse_args("--speed 3").is_err());
+  }
+
+  fn get_phase_sequences(phases : &RangeInclusive<vm::BigByte>, length : usize) -> Vec<Vec<vm::BigByte>> {
+    let mut sequences = Vec::new();
+    visit_phase_sequences(phases, length, &mut |phases| {
+      sequences.push(phases.to_vec());
+      Ok(())
+    }).unwrap();
+    sequences
+  }
+
+  #[test]
+  fn lists_phase_sequences() {
+    assert_eq!(
+      get_phase_sequences(&(0..=2), 2),
+      vec![vec![0, 1], vec![0, 2], vec![1, 0], vec![1, 2], vec![2, 0], vec![2, 1]]
+    );
+    assert_eq!(get_phase_sequences(&(0..=4), 5).len(), 120);
+    let mut visited = 0;
+    let stopped = visit_phase_sequences(&(0..=4), 5, &mut |_| {
+      visited += 1;
+      if visited == 3 { Err("enough") } else { Ok(()) }
+    });
+    assert_eq!((stopped, visited), (Err("enough"), 3));
+  }
+
+  #[test]
+  fn runs_amplifiers_in_series() {
+    let memory = vm::parse_memory(SERIAL);
+    assert_eq!(run_serial(&mut create_cluster(&memory, &[4, 3, 2, 1, 0])), Ok(43210));
+    assert_eq!(run_serial(&mut create_cluster(&memory, &[0, 1, 2, 3, 4])), Ok(1234));
+  }
+
+  #[test]
+  fn runs_amplifiers_in_a_feedback_loop() {
+    let memory = vm::parse_memory(FEEDBACK);
+    assert_eq!(run_feedback(&mut create_cluster(&memory, &[9, 8, 7, 6, 5])), Ok(139629729));
+  }
+
+  #[test]
+  fn rejects_amplifiers_stuck_on_input() {
+    // Reads its phase and a signal, then waits for a third input.
+    let memory = vm::parse_memory("3,20,3,20,3,20,99");
+    let error = Err("Amplifier waits for input without any output");
+    assert_eq!(run_feedback(&mut create_cluster(&memory, &[5, 6])), error);
+    assert_eq!(run_serial(&mut create_cluster(&memory, &[0, 1])), error);
+  }
+
+  #[test]
+  fn finds_best_phase_sequences() {
+    let best = find_best(&vm::parse_memory(SERIAL), &parse_args("").unwrap()).unwrap();
+    assert_eq!((best.phases, best.output), (vec![4, 3, 2, 1, 0], 43210));
+    let best = find_best(&vm::parse_memory(FEEDBACK), &parse_args("--mode feedback").unwrap()).unwrap();
+    assert_eq!((best.phases, best.output), (vec![9, 8, 7, 6, 5], 139629729));
+  }
 }
@@ -0,0 +1,177 @@
+use std::io::prelude::*;
+
+pub type Pixel = u8;
+
+pub const BLACK : Pixel = 0;
+pub const WHITE : Pixel = 1;
+pub const TRANSPARENT : Pixel = 2;
+
+#[derive(Debug, PartialEq)]
+pub enum ParseError {
+  InvalidDigit { position : usize, found : char },
+  IncompleteLayer { pixels : usize, layer_size : usize },
+  NoLayers,
+  // Empty, or too large for its pixels to be counted.
+  InvalidSize { width : usize, height : usize },
+  Io(String),
+}
+
+impl std::fmt::Display for ParseError {
+  fn fmt(&self, f : &mut std::fmt::Formatter) -> std::fmt::Result {
+    match self {
+      ParseError::InvalidDigit { position, found } => {
+        write!(f, "pixel {}: invalid digit '{}', expected 0, 1 or 2", position, found)
+      },
+      ParseError::IncompleteLayer { pixels, layer_size } => {
+        write!(f, "{} pixels is not a whole number of layers of {} pixels", pixels, layer_size)
+      },
+      ParseError::NoLayers => write!(f, "no layers"),
+      ParseError::InvalidSize { width, height } => write!(f, "invalid image size {}x{}", width, height),
+      ParseError::Io(e) => write!(f, "could not read the image: {}", e),
+    }
+  }
+}
+
+// Layers stacked into one image, with the digit counts of its checksum layer.
+pub struct Image {
+  pub width : usize,
+  pub height : usize,
+  pub layers : usize,
+  pixels : Vec<Pixel>,
+  // Digit counts of the layer with the fewest zeros, indexed by digit.
+  pub checksum_counts : [usize; 3],
+}
+
+/*
+ * Composites the layers as their pixels come: a pixel keeps the first color
+ * that is not transparent, so only one layer of pixels is ever kept.
+ */
+pub struct Decoder {
+  width : usize,
+  height : usize,
+  pixels : Vec<Pixel>,
+  cursor : usize,
+  layers : usize,
+  counts : [usize; 3],
+  checksum_counts : Option<[usize; 3]>,
+}
+
+impl Decoder {
+  pub fn new(width : usize, height : usize) -> Result<Decoder, ParseError> {
+    let layer_size = match width.checked_mul(height) {
+      Some(layer_size) if layer_size > 0 => layer_size,
+      _ => return Err(ParseError::InvalidSize { width, height }),
+    };
+    Ok(Decoder {
+      width,
+      height,
+      pixels : vec![TRANSPARENT; layer_size],
+      cursor : 0,
+      layers : 0,
+      counts : [0; 3],
+      checksum_counts : None,
+    })
+  }
+
+  pub fn push(&mut self, pixel : Pixel) {
+    let composite = &mut self.pixels[self.cursor];
+    if *composite == TRANSPARENT {
+      *composite = pixel;
+    }
+    self.counts[pixel as usize] += 1;
+    self.cursor += 1;
+    if self.cursor == self.pixels.len() {
+      if self.checksum_counts.is_none_or(|counts| self.counts[0] < counts[0]) {
+        self.checksum_counts = Some(self.counts);
+      }
+      self.counts = [0; 3];
+      self.cursor = 0;
+      self.layers += 1;
+    }
+  }
+
+  pub fn finish(self) -> Result<Image, ParseError> {
+    if self.cursor != 0 {
+      let layer_size = self.pixels.len();
+      return Err(ParseError::IncompleteLayer { pixels : self.layers * layer_size + self.cursor, layer_size });
+    }
+    let checksum_counts = self.checksum_counts.ok_or(ParseError::NoLayers)?;
+    Ok(Image {
+      width : self.width,
+      height : self.height,
+      layers : self.layers,
+      pixels : self.pixels,
+      checksum_counts,
+    })
+  }
+}
+
+// Reads the digits one at a time, whitespace being skipped.
+pub fn decode(reader : impl Read, width : usize, height : usize) -> Result<Image, ParseError> {
+  let mut decoder = Decoder::new(width, height)?;
+  let mut position = 0;
+  for byte in std::io::BufReader::new(reader).bytes() {
+    let byte = byte.map_err(|e| ParseError::Io(e.to_string()))?;
+    if byte.is_ascii_whitespace() {
+      continue;
+    }
+    match (byte as char).to_digit(3) {
+      Some(digit) => decoder.push(digit as Pixel),
+      None => return Err(ParseError::InvalidDigit { position, found : byte as char }),
+    }
+    position += 1;
+  }
+  decoder.finish()
+}
+
+pub fn get_pixel(image : &Image, x : usize, y : usize) -> Pixel {
+  image.pixels[y * image.width + x]
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn composites_layers() {
+    let image = decode("0222112222120000\n".as_bytes(), 2, 2).unwrap();
+    assert_eq!(image.layers, 4);
+    let pixels : Vec<Pixel> = (0..2).flat_map(|y| (0..2).map(move |x| (x, y))).map(|(x, y)| get_pixel(&image, x, y)).collect();
+    assert_eq!(pixels, [0, 1, 1, 0]);
+    assert_eq!(image.checksum_counts, [0, 2, 2]);
+  }
+
+  #[test]
+  fn rejects_invalid_images() {
+    assert_eq!(decode("0123".as_bytes(), 2, 2).err(), Some(ParseError::InvalidDigit { position : 3, found : '3' }));
+    assert_eq!(
+      decode("01201".as_bytes(), 2, 2).err(),
+      Some(ParseError::IncompleteLayer { pixels : 5, layer_size : 4 })
+    );
+    assert_eq!(decode(" \n".as_bytes(), 2, 2).err(), Some(ParseError::NoLayers));
+    assert_eq!(decode("0".as_bytes(), 0, 2).err(), Some(ParseError::InvalidSize { width : 0, height : 2 }));
+    assert_eq!(
+      decode("0".as_bytes(), usize::MAX, 2).err(),
+      Some(ParseError::InvalidSize { width : usize::MAX, height : 2 })
+    );
+  }
+
+  // Fails after giving a few digits.
+  struct BrokenReader(usize);
+
+  impl Read for BrokenReader {
+    fn read(&mut self, buffer : &mut [u8]) -> std::io::Result<usize> {
+      if self.0 == 0 {
+        return Err(std::io::Error::other("disk on fire"));
+      }
+      self.0 -= 1;
+      buffer[0] = b'0';
+      Ok(1)
+    }
+  }
+
+  #[test]
+  fn reports_read_errors() {
+    assert_eq!(decode(BrokenReader(3), 2, 2).err(), Some(ParseError::Io(String::from("disk on fire"))));
+  }
+}
@@ -0,0 +1,118 @@
+use crate::decoder::{Pixel, TRANSPARENT};
+use common::rng::Rng;
+
+// Digits of the layers one after the other, all of the same size.
+pub fn encode_layers(layers : &[Vec<Pixel>]) -> Result<String, &'static str> {
+  let layer_size = layers.first().ok_or("No layers")?.len();
+  if layer_size == 0 {
+    return Err("Empty layers");
+  }
+  if layers.iter().any(|layer| layer.len() != layer_size) {
+    return Err("Layers of different sizes");
+  }
+  if layers.iter().flatten().any(|pixel| *pixel > TRANSPARENT) {
+    return Err("Invalid pixel");
+  }
+  Ok(layers.iter().flatten().map(|pixel| (b'0' + pixel) as char).collect())
+}
+
+/*
+ * `layer_count` layers compositing to the target, with the pixels of the
+ * mask left transparent. Each other pixel shows on a random layer, the ones
+ * above it being transparent and the ones below it random.
+ */
+pub fn get_layers(target : &[Pixel], mask : &[bool], layer_count : usize, rng : &mut Rng)
+  -> Result<Vec<Vec<Pixel>>, &'static str>
+{
+  if target.len() != mask.len() {
+    return Err("Mask and target of different sizes");
+  }
+  if layer_count == 0 {
+    return Err("No layers");
+  }
+  let mut layers = vec![vec![TRANSPARENT; target.len()]; layer_count];
+  for (i, (pixel, transparent)) in target.iter().zip(mask).enumerate() {
+    if *transparent {
+      continue;
+    }
+    if *pixel >= TRANSPARENT {
+      return Err("Target pixel neither black nor white");
+    }
+    let depth = rng.range(0, layer_count - 1);
+    layers[depth][i] = *pixel;
+    for layer in layers.iter_mut().skip(depth + 1) {
+      layer[i] = rng.range(0, TRANSPARENT as usize) as Pixel;
+    }
+  }
+  Ok(layers)
+}
+
+pub fn encode_image(target : &[Pixel], mask : &[bool], layer_count : usize, rng : &mut Rng)
+  -> Result<String, &'static str>
+{
+  encode_layers(&get_layers(target, mask, layer_count, rng)?)
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::decoder::{decode, get_pixel, Image};
+
+  fn get_pixels(image : &Image) -> Vec<Pixel> {
+    (0..image.height)
+      .flat_map(|y| (0..image.width).map(move |x| (x, y)))
+      .map(|(x, y)| get_pixel(image, x, y))
+      .collect()
+  }
+
+  // Compositing done the slow way: the first pixel that is not transparent.
+  fn composite(layers : &[Vec<Pixel>]) -> Vec<Pixel> {
+    (0..layers[0].len())
+      .map(|i| layers.iter().map(|layer| layer[i]).find(|pixel| *pixel != TRANSPARENT).unwrap_or(TRANSPARENT))
+      .collect()
+  }
+
+  #[test]
+  fn round_trips_images() {
+    let mut rng = Rng::new(2019);
+    for _ in 0..500 {
+      let (width, height) = (rng.range(1, 8), rng.range(1, 8));
+      let layer_count = rng.range(1, 6);
+      let target : Vec<Pixel> = (0..width * height).map(|_| rng.range(0, 1) as Pixel).collect();
+      let mask : Vec<bool> = (0..width * height).map(|_| rng.range(0, 3) == 0).collect();
+      let layers = get_layers(&target, &mask, layer_count, &mut rng).unwrap();
+      let expected : Vec<Pixel> = target.iter().zip(&mask).map(|(pixel, transparent)| {
+        if *transparent { TRANSPARENT } else { *pixel }
+      }).collect();
+      assert_eq!(composite(&layers), expected);
+      let image = decode(encode_layers(&layers).unwrap().as_bytes(), width, height).unwrap();
+      assert_eq!((image.width, image.height, image.layers), (width, height, layer_count));
+      assert_eq!(get_pixels(&image), expected);
+    }
+  }
+
+  #[test]
+  fn composites_any_layers() {
+    let mut rng = Rng::new(8);
+    for _ in 0..500 {
+      let (width, height) = (rng.range(1, 8), rng.range(1, 8));
+      let layers : Vec<Vec<Pixel>> = (0..rng.range(1, 6))
+        .map(|_| (0..width * height).map(|_| rng.range(0, 2) as Pixel).collect())
+        .collect();
+      let image = decode(encode_layers(&layers).unwrap().as_bytes(), width, height).unwrap();
+      assert_eq!(get_pixels(&image), composite(&layers));
+      let zeros = |layer : &Vec<Pixel>| layer.iter().filter(|pixel| **pixel == 0).count();
+      let checksum_layer = layers.iter().min_by_key(|layer| zeros(layer)).unwrap();
+      assert_eq!(image.checksum_counts[0], zeros(checksum_layer));
+    }
+  }
+
+  #[test]
+  fn rejects_invalid_layers() {
+    assert_eq!(encode_layers(&[vec![0, 1], vec![2]]), Err("Layers of different sizes"));
+    assert_eq!(encode_layers(&[vec![0, 3]]), Err("Invalid pixel"));
+    assert_eq!(encode_layers(&[vec![], vec![]]), Err("Empty layers"));
+    assert_eq!(encode_layers(&[vec![0, 1], vec![2, 2]]), Ok(String::from("0122")));
+    assert_eq!(get_layers(&[2], &[false], 1, &mut Rng::new(1)), Err("Target pixel neither black nor white"));
+  }
+}
@@ -1,101 +1,104 @@
 
-use std::{
-  env,
-  fs,
-  io::{prelude::*},
-  path,
-};
+mod decoder;
+mod encoder;
 
-type Pixel = u8;
-type Line = Vec<Pixel>;
-type Layer = Vec<Line>;
-type Image = Vec<Layer>;
+use decoder::{get_pixel, Image};
+use std::{env, fs, path};
 
-const IMAGE_WIDTH : usize = 25;
-const IMAGE_HEIGHT : usize = 6;
-
-fn parse_file(filename : impl AsRef<path::Path>) -> Image {
-  let mut file = fs::File::open(filename).expect("File not found");
-  let mut contents = String::new();
-  file.read_to_string(&mut contents).expect("Could not read file");
-  let content : Vec<u8> = contents.trim().chars()
-    .map(|c| c.to_digit(3).expect("Could not parse number") as Pixel)
-    .collect();
-  let layer_count = content.len() / (IMAGE_WIDTH * IMAGE_HEIGHT);
-  let mut cursor = 0;
-  let mut image = Image::new();
-  for _ in 0..layer_count {
-    let mut layer = Layer::new();
-    for _ in 0..IMAGE_HEIGHT {
-      let mut line = Line::new();
-      for _ in 0..IMAGE_WIDTH {
-        line.push(content[cursor]);
-        cursor += 1;
-      }
-      layer.push(line);
-    }
-    image.push(layer);
-  }
-  image
+fn parse_file(filename : impl AsRef<path::Path>, width : usize, height : usize) -> Result<Image, decoder::ParseError> {
+  let file = fs::File::open(filename).expect("File not found");
+  decoder::decode(file, width, height)
 }
 
-fn get_digit_count(layer : &Layer, digit : Pixel) -> usize {
-  let mut total_digits = 0;
-  layer.iter().for_each(|line| total_digits += line.iter().filter(|&d| *d == digit).count());
-  total_digits
-}
-
-fn part1(image : &Image) {
-  let before = std::time::Instant::now();
-  let mut least_zeros = get_digit_count(&image[0], 0);
-  let mut winning_layer_id = 0;
-  for layer_id in 1..image.len() {
-    let zeros = get_digit_count(&image[layer_id], 0);
-    if zeros < least_zeros {
-      least_zeros = zeros;
-      winning_layer_id = layer_id;
-    }
-  }
-  let one = get_digit_count(&image[winning_layer_id], 1);
-  let two = get_digit_count(&image[winning_layer_id], 2);
-  let result = one * two;
-
-  println!("Elapsed time: {:.2?}", before.elapsed());
+// The checksum layer is found while decoding, so decoding is what part 1 costs.
+fn part1(image : &Image, decode_time : std::time::Duration) {
+  let counts = image.checksum_counts;
+  let result = counts[1] * counts[2];
+  println!("Elapsed time: {:.2?}", decode_time);
   println!("Part1: result: {}", result);
 }
 
-fn get_pixel(image : &Image, w : usize, h : usize) -> Pixel {
-  for layer in image {
-    let layer_pixel = layer[h][w];
-    if layer_pixel != 2 {
-      return layer_pixel
-    }
-  }
-  2
-}
-
 fn part2(image : &Image) {
   let before = std::time::Instant::now();
   let mut combined_layer = Vec::new();
-  for h in 0..IMAGE_HEIGHT {
+  for h in 0..image.height {
     let mut line : String = String::new();
-    for w in 0..IMAGE_WIDTH {
+    for w in 0..image.width {
       let pixel = get_pixel(image, w, h);
       let pixel_char = match pixel {
-        0 => ' ',
-        1 => 'X',
-        2 => '?',
+        decoder::BLACK => ' ',
+        decoder::WHITE => 'X',
+        decoder::TRANSPARENT => '?',
         _ => panic!("Impossible happened"),
       };
       line.push(pixel_char)
     }
     combined_layer.push(line);
   }
+  let letters = common::ocr::recognize_text(&combined_layer.join("\n"), 'X');
   println!("Elapsed time: {:.2?}", before.elapsed());
   println!("Part2: result:\n");
   for line in combined_layer {
-    println!("{}", line);    
+    println!("{}", line);
   }
+  match letters {
+    Ok(text) => println!("\nPart2: letters: {}", text),
+    Err(e) => println!("\nPart2: could not read the letters: {}", e),
+  }
+}
+
+/*
+ * The image hidden again under `layer_count` layers of noise, its transparent
+ * pixels staying transparent.
+ */
+fn reencode(image : &Image, layer_count : usize, filename : &str) -> Result<(), String> {
+  let pixels : Vec<decoder::Pixel> = (0..image.height)
+    .flat_map(|y| (0..image.width).map(move |x| (x, y)))
+    .map(|(x, y)| get_pixel(image, x, y))
+    .collect();
+  let mask : Vec<bool> = pixels.iter().map(|pixel| *pixel == decoder::TRANSPARENT).collect();
+  // Masked pixels stay transparent whatever their target color.
+  let target : Vec<decoder::Pixel> = pixels.iter().map(|pixel| (*pixel).min(decoder::WHITE)).collect();
+  let mut rng = common::rng::Rng::new(layer_count as u64);
+  let digits = encoder::encode_image(&target, &mask, layer_count, &mut rng)?;
+  fs::write(filename, digits + "\n").map_err(|e| format!("Could not write {}: {}", filename, e))
+}
+
+struct Options {
+  width : usize,
+  height : usize,
+  encode : Option<String>,
+  layers : usize,
+}
+
+fn get_size(value : Option<&String>) -> Result<usize, &'static str> {
+  match value.ok_or("Missing image size")?.parse::<usize>() {
+    Ok(size) if size > 0 => Ok(size),
+    _ => Err("Invalid image size"),
+  }
+}
+
+fn parse_options(args : &[String]) -> Result<Options, &'static str> {
+  let mut options = Options {
+    width : 25,
+    height : 6,
+    encode : None,
+    layers : 100,
+  };
+  let mut it = args.iter();
+  while let Some(arg) = it.next() {
+    match arg.as_str() {
+      "--width" => options.width = get_size(it.next())?,
+      "--height" => options.height = get_size(it.next())?,
+      "--encode" => options.encode = Some(it.next().ok_or("Missing value after --encode")?.clone()),
+      "--layers" => {
+        options.layers = it.next().ok_or("Missing value after --layers")?
+          .parse::<usize>().map_err(|_| "Invalid number of layers")?
+      },
+      _ => return Err("Unknown option"),
+    }
+  }
+  Ok(options)
 }
 
 fn main() {
@@ -104,8 +107,19 @@ fn main() {
     panic!("Not enough arguments");
   }
   let filename = &args[1];
+  let options = parse_options(&args[2..]).expect("Invalid arguments");
   println!("Loading file {}", filename);
-  let image = parse_file(filename);
-  part1(&image);
-  part2(&image);  
+  let before = std::time::Instant::now();
+  let image = match parse_file(filename, options.width, options.height) {
+    Ok(image) => image,
+    Err(e) => panic!("Invalid image: {}", e),
+  };
+  let decode_time = before.elapsed();
+  println!("Image: {} layers of {}x{}", image.layers, image.width, image.height);
+  part1(&image, decode_time);
+  part2(&image);
+  if let Some(filename) = options.encode {
+    reencode(&image, options.layers, &filename).unwrap();
+    println!("Image encoded in {} layers to {}", options.layers, filename);
+  }
 }
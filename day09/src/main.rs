@@ -255,3 +255,26 @@ fn main() {
   let memory = parse_file(filename);
   part1(&memory);
 }
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use common::intcode_conformance::{run_suite, Execution, Feature};
+
+  // This VM takes a single input and only reports its last output.
+  fn execute(program : &[BigByte], inputs : &[BigByte]) -> Result<Execution, &'static str> {
+    let memory : Memory = (0..).zip(program.iter().cloned()).collect();
+    let mut vm = create_vm(&memory, inputs.first().cloned().unwrap_or(0));
+    let output = vm.run()?;
+    Ok(Execution {
+      outputs : output.into_iter().collect(),
+      memory : (0..program.len() as BigByte).map(|i| vm.get_byte(i)).collect(),
+    })
+  }
+
+  #[test]
+  fn conformance() {
+    let failures = run_suite(&[Feature::RelativeMode, Feature::LargeNumbers, Feature::GrowingMemory], execute);
+    assert!(failures.is_empty(), "{}", failures.join("\n"));
+  }
+}
@@ -0,0 +1,515 @@
+use common::vm::{self, BigByte, Memory, Mode, Parameters};
+use std::collections::{BTreeMap, BTreeSet, HashSet};
+
+pub struct Instruction {
+	pub address: BigByte,
+	pub op_code: BigByte,
+	pub parameters: Parameters,
+	pub arguments: Vec<BigByte>,
+}
+
+impl Instruction {
+	fn end(&self) -> BigByte {
+		self.address + self.arguments.len() as BigByte + 1
+	}
+
+	// The value the instruction was decoded from.
+	fn get_value(&self) -> BigByte {
+		self.parameters
+			.iter()
+			.zip([100, 1000, 10000].iter())
+			.fold(self.op_code, |value, (mode, unit)| value + *mode as BigByte * unit)
+	}
+
+	fn is_jump(&self) -> bool {
+		self.op_code == 5 || self.op_code == 6
+	}
+
+	fn get_write_parameter(&self) -> Option<usize> {
+		match self.op_code {
+			1 | 2 | 7 | 8 => Some(2),
+			3 => Some(0),
+			_ => None,
+		}
+	}
+}
+
+/*
+ * Cells the reachable code may write. Position mode writes have a known
+ * address, relative mode ones depend on the relative base at runtime so they
+ * may reach any cell.
+ */
+#[derive(Default, PartialEq)]
+struct Writes {
+	cells: HashSet<BigByte>,
+	relative: bool,
+}
+
+impl Writes {
+	fn get(instructions: &BTreeMap<BigByte, Instruction>) -> Writes {
+		let mut writes = Writes::default();
+		for instruction in instructions.values() {
+			let parameter = match instruction.get_write_parameter() {
+				Some(parameter) => parameter,
+				None => continue,
+			};
+			match instruction.parameters[parameter] {
+				Mode::Position => {
+					writes.cells.insert(instruction.arguments[parameter]);
+				}
+				Mode::Relative => writes.relative = true,
+				Mode::Immediate => (),
+			}
+		}
+		writes
+	}
+
+	fn may_change(&self, address: BigByte) -> bool {
+		self.relative || self.cells.contains(&address)
+	}
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Edge {
+	// Execution continues with the following instruction.
+	Next(BigByte),
+	// Jump to an immediate target.
+	Jump(BigByte),
+	// Where a call resumes, the callee returning through an indirect jump.
+	Return(BigByte),
+	// Jump whose target is read from memory and cannot be resolved.
+	Indirect,
+}
+
+impl Edge {
+	fn get_target(&self) -> Option<BigByte> {
+		match self {
+			Edge::Next(target) | Edge::Jump(target) | Edge::Return(target) => Some(*target),
+			Edge::Indirect => None,
+		}
+	}
+}
+
+pub struct Block {
+	pub addresses: Vec<BigByte>,
+	pub edges: Vec<Edge>,
+}
+
+// `rewritten` is set when the program may write the instruction before it runs.
+#[derive(Debug, PartialEq)]
+pub enum Problem {
+	InvalidOpcode { address: BigByte, value: BigByte, rewritten: bool },
+	InvalidMode { address: BigByte, value: BigByte, rewritten: bool },
+	ImmediateWrite { address: BigByte, rewritten: bool },
+	SelfModification { address: BigByte, target: BigByte },
+	Unreachable { first: BigByte, last: BigByte },
+}
+
+impl Problem {
+	// Errors are certain to fail at runtime once reached, the rest only deserve a look.
+	pub fn is_error(&self) -> bool {
+		match self {
+			Problem::InvalidOpcode { rewritten, .. }
+			| Problem::InvalidMode { rewritten, .. }
+			| Problem::ImmediateWrite { rewritten, .. } => !rewritten,
+			Problem::SelfModification { .. } | Problem::Unreachable { .. } => false,
+		}
+	}
+
+	// Address of an instruction that cannot run as it is.
+	fn get_invalid_address(&self) -> Option<BigByte> {
+		match self {
+			Problem::InvalidOpcode { address, .. } | Problem::InvalidMode { address, .. } => Some(*address),
+			_ => None,
+		}
+	}
+}
+
+impl std::fmt::Display for Problem {
+	fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+		let rewritten = match self {
+			Problem::InvalidOpcode { address, value, rewritten } => {
+				write!(f, "{}: invalid opcode in {}", address, value)?;
+				rewritten
+			}
+			Problem::InvalidMode { address, value, rewritten } => {
+				write!(f, "{}: invalid parameter mode in {}", address, value)?;
+				rewritten
+			}
+			Problem::ImmediateWrite { address, rewritten } => {
+				write!(f, "{}: result parameter in immediate mode", address)?;
+				rewritten
+			}
+			Problem::SelfModification { address, target } => {
+				return write!(f, "{}: writes into code at {}", address, target);
+			}
+			Problem::Unreachable { first, last } => {
+				return write!(f, "{}..{}: unreachable", first, last);
+			}
+		};
+		if *rewritten {
+			write!(f, ", unless rewritten before it runs")?;
+		}
+		Ok(())
+	}
+}
+
+impl std::fmt::Display for Instruction {
+	fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+		let name = match self.op_code {
+			1 => "add",
+			2 => "mul",
+			3 => "in",
+			4 => "out",
+			5 => "jnz",
+			6 => "jz",
+			7 => "lt",
+			8 => "eq",
+			9 => "arb",
+			99 => "hlt",
+			_ => return write!(f, "{}: data {}", self.address, self.get_value()),
+		};
+		let arguments: Vec<String> = self
+			.arguments
+			.iter()
+			.zip(self.parameters.iter())
+			.map(|(argument, mode)| match mode {
+				Mode::Position => format!("[{}]", argument),
+				Mode::Immediate => format!("{}", argument),
+				Mode::Relative => format!("[rb{:+}]", argument),
+			})
+			.collect();
+		if arguments.is_empty() {
+			write!(f, "{}: {}", self.address, name)
+		} else {
+			write!(f, "{}: {} {}", self.address, name, arguments.join(", "))
+		}
+	}
+}
+
+pub struct Analysis {
+	pub instructions: BTreeMap<BigByte, Instruction>,
+	pub blocks: Vec<Block>,
+	pub problems: Vec<Problem>,
+}
+
+struct Exploration {
+	instructions: BTreeMap<BigByte, Instruction>,
+	edges: BTreeMap<BigByte, Vec<Edge>>,
+	problems: Vec<Problem>,
+}
+
+fn get_byte(memory: &Memory, address: BigByte) -> BigByte {
+	memory.get(&address).cloned().unwrap_or(0)
+}
+
+fn get_argument_count(op_code: BigByte) -> Option<usize> {
+	match op_code {
+		1 | 2 | 7 | 8 => Some(3),
+		5 | 6 => Some(2),
+		3 | 4 | 9 => Some(1),
+		99 => Some(0),
+		_ => None,
+	}
+}
+
+/*
+ * Value of a read parameter when it is known before running: immediates, and
+ * cells that the reachable code never writes.
+ */
+fn get_constant(memory: &Memory, writes: &Writes, mode: Mode, argument: BigByte) -> Option<BigByte> {
+	match mode {
+		Mode::Immediate => Some(argument),
+		Mode::Position if !writes.may_change(argument) => Some(get_byte(memory, argument)),
+		_ => None,
+	}
+}
+
+/*
+ * Calls push their return address with a constant store right before the
+ * jump, so the code after the jump is reachable once the callee returns.
+ */
+fn get_return_address(instructions: &BTreeMap<BigByte, Instruction>, jump: &Instruction) -> Option<BigByte> {
+	let store = instructions.get(&(jump.address - 4))?;
+	if store.parameters[0] != Mode::Immediate || store.parameters[1] != Mode::Immediate {
+		return None;
+	}
+	let value = match store.op_code {
+		1 => store.arguments[0].checked_add(store.arguments[1]),
+		2 => store.arguments[0].checked_mul(store.arguments[1]),
+		_ => None,
+	};
+	if value == Some(jump.end()) {
+		value
+	} else {
+		None
+	}
+}
+
+fn get_edges(
+	memory: &Memory,
+	writes: &Writes,
+	instructions: &BTreeMap<BigByte, Instruction>,
+	instruction: &Instruction,
+) -> Vec<Edge> {
+	match instruction.op_code {
+		99 => Vec::new(),
+		5 | 6 => {
+			let condition = get_constant(memory, writes, instruction.parameters[0], instruction.arguments[0]);
+			let taken = condition.map(|value| (value != 0) == (instruction.op_code == 5));
+			let mut edges = Vec::new();
+			if taken != Some(false) {
+				edges.push(match instruction.parameters[1] {
+					Mode::Immediate => Edge::Jump(instruction.arguments[1]),
+					_ => Edge::Indirect,
+				});
+				if let Some(address) = get_return_address(instructions, instruction) {
+					edges.push(Edge::Return(address));
+				}
+			}
+			if taken != Some(true) {
+				edges.push(Edge::Next(instruction.end()));
+			}
+			edges
+		}
+		_ => vec![Edge::Next(instruction.end())],
+	}
+}
+
+fn explore(memory: &Memory, writes: &Writes) -> Exploration {
+	let mut exploration = Exploration {
+		instructions: BTreeMap::new(),
+		edges: BTreeMap::new(),
+		problems: Vec::new(),
+	};
+	let mut visited = HashSet::new();
+	let mut pending = vec![0];
+	while let Some(address) = pending.pop() {
+		if !visited.insert(address) {
+			continue;
+		}
+		let value = get_byte(memory, address);
+		let (op_code, parameters) = match vm::get_method(value) {
+			Ok(method) => method,
+			Err(_) => {
+				exploration.problems.push(Problem::InvalidMode {
+					address,
+					value,
+					rewritten: writes.may_change(address),
+				});
+				continue;
+			}
+		};
+		let count = match get_argument_count(op_code) {
+			Some(count) => count,
+			None => {
+				exploration.problems.push(Problem::InvalidOpcode {
+					address,
+					value,
+					rewritten: writes.may_change(address),
+				});
+				continue;
+			}
+		};
+		let instruction = Instruction {
+			address,
+			op_code,
+			parameters,
+			arguments: (1..=count as BigByte).map(|i| get_byte(memory, address + i)).collect(),
+		};
+		let edges = get_edges(memory, writes, &exploration.instructions, &instruction);
+		pending.extend(edges.iter().rev().filter_map(|edge| edge.get_target()));
+		exploration.instructions.insert(address, instruction);
+		exploration.edges.insert(address, edges);
+	}
+	exploration
+}
+
+fn get_blocks(exploration: &Exploration) -> Vec<Block> {
+	let mut leaders = BTreeSet::new();
+	leaders.insert(0);
+	for instruction in exploration.instructions.values().filter(|instruction| instruction.is_jump()) {
+		leaders.extend(exploration.edges[&instruction.address].iter().filter_map(Edge::get_target));
+	}
+	let mut blocks = Vec::new();
+	for &leader in leaders.iter().filter(|leader| exploration.instructions.contains_key(leader)) {
+		let mut addresses = vec![leader];
+		let mut address = leader;
+		loop {
+			let edges = &exploration.edges[&address];
+			match edges.as_slice() {
+				[Edge::Next(next)]
+					if !exploration.instructions[&address].is_jump()
+						&& !leaders.contains(next)
+						&& exploration.instructions.contains_key(next) =>
+				{
+					address = *next;
+					addresses.push(address);
+				}
+				_ => {
+					blocks.push(Block {
+						addresses,
+						edges: edges.clone(),
+					});
+					break;
+				}
+			}
+		}
+	}
+	blocks
+}
+
+fn get_unreachable(memory: &Memory, code: &HashSet<BigByte>) -> Vec<Problem> {
+	let length = memory.keys().max().map_or(0, |last| last + 1);
+	let mut problems = Vec::new();
+	let mut first = None;
+	for address in 0..=length {
+		match (first, address < length && !code.contains(&address)) {
+			(None, true) => first = Some(address),
+			(Some(start), false) => {
+				problems.push(Problem::Unreachable { first: start, last: address - 1 });
+				first = None;
+			}
+			_ => (),
+		}
+	}
+	problems
+}
+
+/*
+ * Walks the code reachable from address 0 without running it. Indirect jumps
+ * are not followed, so code only reached through them is reported unreachable.
+ */
+pub fn analyze(memory: &Memory) -> Analysis {
+	let mut writes = Writes::default();
+	let mut exploration = explore(memory, &writes);
+	loop {
+		let found = Writes::get(&exploration.instructions);
+		if found == writes {
+			break;
+		}
+		writes = found;
+		exploration = explore(memory, &writes);
+	}
+	let mut code: HashSet<BigByte> = exploration
+		.instructions
+		.values()
+		.flat_map(|instruction| instruction.address..instruction.end())
+		.collect();
+	code.extend(exploration.problems.iter().filter_map(Problem::get_invalid_address));
+	let mut problems = std::mem::take(&mut exploration.problems);
+	for instruction in exploration.instructions.values() {
+		let parameter = match instruction.get_write_parameter() {
+			Some(parameter) => parameter,
+			None => continue,
+		};
+		match instruction.parameters[parameter] {
+			Mode::Immediate => problems.push(Problem::ImmediateWrite {
+				address: instruction.address,
+				rewritten: writes.may_change(instruction.address),
+			}),
+			Mode::Position if code.contains(&instruction.arguments[parameter]) => {
+				problems.push(Problem::SelfModification {
+					address: instruction.address,
+					target: instruction.arguments[parameter],
+				})
+			}
+			_ => (),
+		}
+	}
+	problems.extend(get_unreachable(memory, &code));
+	Analysis {
+		blocks: get_blocks(&exploration),
+		instructions: exploration.instructions,
+		problems,
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn analyze_program(program: &str) -> Analysis {
+		analyze(&vm::parse_memory(program))
+	}
+
+	fn get_errors(analysis: &Analysis) -> Vec<&Problem> {
+		analysis.problems.iter().filter(|problem| problem.is_error()).collect()
+	}
+
+	#[test]
+	fn prints_unknown_opcodes_as_data() {
+		let instruction = Instruction {
+			address: 3,
+			op_code: 42,
+			parameters: [Mode::Immediate, Mode::Position, Mode::Relative],
+			arguments: Vec::new(),
+		};
+		assert_eq!(instruction.to_string(), "3: data 20142");
+	}
+
+	#[test]
+	fn rejects_immediate_writes() {
+		let analysis = analyze_program("11101,1,1,5,99");
+		assert_eq!(get_errors(&analysis), [&Problem::ImmediateWrite { address: 0, rewritten: false }]);
+	}
+
+	#[test]
+	fn rejects_invalid_modes() {
+		let analysis = analyze_program("1301,0,0,0,99");
+		assert_eq!(
+			get_errors(&analysis),
+			[&Problem::InvalidMode { address: 0, value: 1301, rewritten: false }]
+		);
+	}
+
+	#[test]
+	fn accepts_self_modification() {
+		// The add turns the invalid opcode 42 into a halt before it runs.
+		let analysis = analyze_program("1101,0,99,4,42");
+		assert!(get_errors(&analysis).is_empty());
+		assert_eq!(
+			analysis.problems,
+			[
+				Problem::InvalidOpcode { address: 4, value: 42, rewritten: true },
+				Problem::SelfModification { address: 0, target: 4 },
+			]
+		);
+	}
+
+	#[test]
+	fn follows_relative_writes() {
+		// The relative mode add at 3 writes 2 into cell 9, through an address
+		// the analysis does not know, so the condition read at 0 is unknown.
+		let analysis = analyze_program("1005,9,8,21101,1,1,9,99,99,0");
+		assert!(analysis.instructions.contains_key(&8));
+		assert!(get_errors(&analysis).is_empty());
+	}
+
+	#[test]
+	fn reports_unreachable_data() {
+		let analysis = analyze_program("1105,1,7,1,2,3,4,99");
+		assert_eq!(analysis.problems, [Problem::Unreachable { first: 3, last: 6 }]);
+		assert_eq!(analysis.instructions.keys().cloned().collect::<Vec<BigByte>>(), [0, 7]);
+	}
+
+	#[test]
+	fn links_calls_to_their_return() {
+		// Stores the return address 7 in cell 11, then calls the function at 8.
+		let analysis = analyze_program("1101,0,7,11,1105,1,8,99,106,0,11,0");
+		let blocks: Vec<(Vec<BigByte>, Vec<Edge>)> = analysis
+			.blocks
+			.iter()
+			.map(|block| (block.addresses.clone(), block.edges.clone()))
+			.collect();
+		assert_eq!(
+			blocks,
+			[
+				(vec![0, 4], vec![Edge::Jump(8), Edge::Return(7)]),
+				(vec![7], vec![]),
+				(vec![8], vec![Edge::Indirect]),
+			]
+		);
+		assert_eq!(analysis.problems, [Problem::Unreachable { first: 11, last: 11 }]);
+	}
+}
@@ -0,0 +1,112 @@
+use crate::analysis::{Analysis, Edge};
+use common::vm::BigByte;
+
+// Labels are quoted strings in the dot language.
+fn escape(text: &str) -> String {
+	text.replace('\\', "\\\\").replace('"', "\\\"")
+}
+
+fn get_edge(analysis: &Analysis, from: BigByte, to: BigByte, attributes: &str) -> String {
+	let attributes = if attributes.is_empty() {
+		String::new()
+	} else {
+		format!(" [{}]", attributes)
+	};
+	if analysis.instructions.contains_key(&to) {
+		format!("  b{} -> b{}{};\n", from, to, attributes)
+	} else {
+		format!(
+			"  invalid{} [label=\"{}: not decodable\", color=red];\n  b{} -> invalid{}{};\n",
+			to, to, from, to, attributes
+		)
+	}
+}
+
+/*
+ * Writes the basic blocks found by the analysis as a Graphviz digraph. Jumps
+ * reading their target from memory point to a dashed "unresolved" node.
+ */
+fn get_dot(analysis: &Analysis) -> String {
+	let mut dot = String::from("digraph intcode {\n  node [shape=box, fontname=\"monospace\"];\n");
+	for block in &analysis.blocks {
+		let start = block.addresses[0];
+		let label: String = block
+			.addresses
+			.iter()
+			.map(|address| format!("{}\\l", escape(&analysis.instructions[address].to_string())))
+			.collect();
+		dot.push_str(&format!("  b{} [label=\"{}\"];\n", start, label));
+		for edge in &block.edges {
+			match edge {
+				Edge::Next(target) => dot.push_str(&get_edge(analysis, start, *target, "")),
+				Edge::Jump(target) => dot.push_str(&get_edge(analysis, start, *target, "label=\"jump\"")),
+				Edge::Return(target) => dot.push_str(&get_edge(
+					analysis,
+					start,
+					*target,
+					"label=\"return\", style=dashed",
+				)),
+				Edge::Indirect => dot.push_str(&format!(
+					"  unresolved{} [label=\"?\", shape=circle, style=dashed];\n  b{} -> unresolved{} [label=\"unresolved\", style=dashed];\n",
+					start, start, start
+				)),
+			}
+		}
+	}
+	dot.push_str("}\n");
+	dot
+}
+
+pub fn write_dot(analysis: &Analysis, filename: impl AsRef<std::path::Path>) -> std::io::Result<()> {
+	std::fs::write(filename, get_dot(analysis))
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::analysis::analyze;
+	use common::vm;
+
+	fn get_program_dot(program: &str) -> String {
+		get_dot(&analyze(&vm::parse_memory(program)))
+	}
+
+	#[test]
+	fn draws_calls() {
+		let dot = get_program_dot("1101,0,7,11,1105,1,8,99,106,0,11,0");
+		assert_eq!(
+			dot.lines().collect::<Vec<&str>>(),
+			[
+				"digraph intcode {",
+				"  node [shape=box, fontname=\"monospace\"];",
+				"  b0 [label=\"0: add 0, 7, [11]\\l4: jnz 1, 8\\l\"];",
+				"  b0 -> b8 [label=\"jump\"];",
+				"  b0 -> b7 [label=\"return\", style=dashed];",
+				"  b7 [label=\"7: hlt\\l\"];",
+				"  b8 [label=\"8: jz 0, [11]\\l\"];",
+				"  unresolved8 [label=\"?\", shape=circle, style=dashed];",
+				"  b8 -> unresolved8 [label=\"unresolved\", style=dashed];",
+				"}",
+			]
+		);
+	}
+
+	#[test]
+	fn draws_undecodable_targets() {
+		// The input makes the condition unknown, the jump target holds 42.
+		let dot = get_program_dot("3,9,1005,9,8,99,99,99,42,0");
+		assert_eq!(
+			dot.lines().collect::<Vec<&str>>(),
+			[
+				"digraph intcode {",
+				"  node [shape=box, fontname=\"monospace\"];",
+				"  b0 [label=\"0: in [9]\\l2: jnz [9], 8\\l\"];",
+				"  invalid8 [label=\"8: not decodable\", color=red];",
+				"  b0 -> invalid8 [label=\"jump\"];",
+				"  b0 -> b5;",
+				"  b5 [label=\"5: hlt\\l\"];",
+				"}",
+			]
+		);
+	}
+}
@@ -0,0 +1,344 @@
+use common::vm::{BigByte, Snapshot, VirtualMachine};
+
+/*
+ * How a robot talks to its brain: the brain is given one sensor value at a
+ * time and answers with commands spread over a fixed number of outputs, which
+ * change the world the next sensor value is read from.
+ */
+pub trait Protocol {
+	type Command;
+
+	// Exact world state, compared when fingerprints match.
+	type State: PartialEq;
+
+	// Number of outputs making up one command.
+	const COMMAND_SIZE: usize;
+
+	fn sense(&self) -> BigByte;
+
+	fn decode(&self, outputs: &[BigByte]) -> Result<Self::Command, &'static str>;
+
+	fn apply(&mut self, command: Self::Command);
+
+	/*
+	 * Hash of the world state the brain can observe, used to find cycles.
+	 * Worlds returning None are never checked.
+	 */
+	fn get_fingerprint(&self) -> Option<u64> {
+		None
+	}
+
+	// Worlds returning None are trusted on their fingerprint.
+	fn get_state(&self) -> Option<Self::State> {
+		None
+	}
+}
+
+// The brain of a robot, an Intcode program or anything speaking the same way.
+pub trait Controller {
+	// Exact controller state, compared when fingerprints match.
+	type State: PartialEq;
+
+	// Outputs produced from one sensor value, None once the controller stopped.
+	fn react(&mut self, input: BigByte) -> Result<Option<Vec<BigByte>>, &'static str>;
+
+	// Hash of the controller state, None when it cannot be known.
+	fn get_fingerprint(&self) -> Option<u64> {
+		None
+	}
+
+	// Controllers returning None are trusted on their fingerprint.
+	fn get_state(&self) -> Option<Self::State> {
+		None
+	}
+}
+
+impl Controller for VirtualMachine {
+	type State = Snapshot;
+
+	fn react(&mut self, input: BigByte) -> Result<Option<Vec<BigByte>>, &'static str> {
+		if !self.is_running() {
+			return Ok(None);
+		}
+		self.push_input(input);
+		self.run()?;
+		Ok(Some(std::iter::from_fn(|| self.pop_output()).collect()))
+	}
+
+	fn get_fingerprint(&self) -> Option<u64> {
+		Some(VirtualMachine::get_fingerprint(self))
+	}
+
+	fn get_state(&self) -> Option<Snapshot> {
+		Some(self.get_snapshot())
+	}
+}
+
+// Scripted controllers, whose state is out of reach so cycles go unnoticed.
+impl<F: FnMut(BigByte) -> Option<Vec<BigByte>>> Controller for F {
+	type State = ();
+
+	fn react(&mut self, input: BigByte) -> Result<Option<Vec<BigByte>>, &'static str> {
+		Ok(self(input))
+	}
+}
+
+#[derive(Debug, PartialEq)]
+pub enum Outcome {
+	Halted {
+		commands: usize,
+	},
+	/*
+	 * The world and the controller came back to a state already seen, so they
+	 * would repeat the same `length` commands forever from command `entry`.
+	 */
+	Cycle {
+		entry: usize,
+		length: usize,
+	},
+	// Still running when the command limit was reached.
+	Interrupted {
+		commands: usize,
+	},
+}
+
+// Exact states at a command whose fingerprints matched an earlier one.
+struct Suspect<C: Controller, P: Protocol> {
+	entry: usize,
+	length: usize,
+	brain: Option<C::State>,
+	world: Option<P::State>,
+}
+
+/*
+ * Runs the controller until it stops, cycles or carries out `limit` commands.
+ * The outputs of every reaction are decoded and applied in order before the
+ * sensor is read again. When the fingerprints of the controller and the world
+ * match those of an earlier command, `length` commands before, their exact
+ * states are kept and the run goes on for `length` more commands: the cycle is
+ * only reported if they come back to the same states, a hash collision being
+ * ignored. Earlier states are not kept, so the entry of a real cycle relies on
+ * the fingerprints. A program counting steps in memory never repeats itself
+ * and is not caught.
+ *
+ * Every command checked with fingerprints leaves one entry of two hashes and
+ * a count in a map, a few dozen bytes, so a run that never cycles grows it
+ * until it stops. Pass a `limit` to bound it.
+ */
+pub fn drive<C: Controller, P: Protocol>(
+	controller: &mut C,
+	protocol: &mut P,
+	limit: Option<usize>,
+) -> Result<Outcome, &'static str> {
+	let mut commands = 0;
+	let mut outputs = Vec::new();
+	let mut seen = std::collections::HashMap::new();
+	let mut suspect: Option<Suspect<C, P>> = None;
+	loop {
+		if outputs.is_empty() {
+			if limit.is_some_and(|limit| commands >= limit) {
+				return Ok(Outcome::Interrupted { commands });
+			}
+			if let Some(Suspect { entry, length, brain, world }) = &suspect {
+				// One reaction may carry out several commands at once, so the
+				// states are compared at the first chance and the cycle is as
+				// long as the run since they were kept.
+				if commands - entry >= 2 * length {
+					if *brain == controller.get_state() && *world == protocol.get_state() {
+						return Ok(Outcome::Cycle {
+							entry: *entry,
+							length: commands - entry - length,
+						});
+					}
+					suspect = None;
+				}
+			}
+			if let (Some(brain), Some(world)) = (controller.get_fingerprint(), protocol.get_fingerprint()) {
+				if let Some(entry) = seen.insert((brain, world), commands) {
+					if suspect.is_none() {
+						suspect = Some(Suspect {
+							entry,
+							length: commands - entry,
+							brain: controller.get_state(),
+							world: protocol.get_state(),
+						});
+					}
+				}
+			}
+		}
+		let reaction = match controller.react(protocol.sense())? {
+			Some(reaction) => reaction,
+			None => break,
+		};
+		for output in reaction {
+			outputs.push(output);
+			if outputs.len() == P::COMMAND_SIZE {
+				let command = protocol.decode(&outputs)?;
+				protocol.apply(command);
+				outputs.clear();
+				commands += 1;
+			}
+		}
+	}
+	if outputs.is_empty() {
+		Ok(Outcome::Halted { commands })
+	} else {
+		Err("Controller stopped in the middle of a command")
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use common::vm;
+
+	// Counts down, the program echoing every value twice until it reads 0.
+	struct Countdown {
+		value: BigByte,
+		received: Vec<(BigByte, BigByte)>,
+	}
+
+	impl Protocol for Countdown {
+		type Command = (BigByte, BigByte);
+
+		type State = BigByte;
+
+		const COMMAND_SIZE: usize = 2;
+
+		fn sense(&self) -> BigByte {
+			self.value
+		}
+
+		fn decode(&self, outputs: &[BigByte]) -> Result<Self::Command, &'static str> {
+			Ok((outputs[0], outputs[1]))
+		}
+
+		fn apply(&mut self, command: Self::Command) {
+			self.received.push(command);
+			self.value -= 1;
+		}
+
+		// Every value hashes the same, as if they all collided.
+		fn get_fingerprint(&self) -> Option<u64> {
+			Some(0)
+		}
+
+		fn get_state(&self) -> Option<BigByte> {
+			Some(self.value)
+		}
+	}
+
+	fn create_program(program: &str) -> VirtualMachine {
+		vm::create_vm(vm::parse_memory(program), vm::Tier::Interpreter)
+	}
+
+	#[test]
+	fn applies_every_command() {
+		let mut vm = create_program("3,100,1006,100,12,4,100,4,100,1105,1,0,99");
+		let mut countdown = Countdown {
+			value: 3,
+			received: Vec::new(),
+		};
+		assert_eq!(drive(&mut vm, &mut countdown, None), Ok(Outcome::Halted { commands: 3 }));
+		assert_eq!(countdown.received, vec![(3, 3), (2, 2), (1, 1)]);
+	}
+
+	// Echoes every value twice until it reads 0, without any state of its own.
+	struct Echo;
+
+	impl Controller for Echo {
+		type State = ();
+
+		fn react(&mut self, input: BigByte) -> Result<Option<Vec<BigByte>>, &'static str> {
+			Ok(if input == 0 { None } else { Some(vec![input, input]) })
+		}
+
+		fn get_fingerprint(&self) -> Option<u64> {
+			Some(0)
+		}
+
+		fn get_state(&self) -> Option<()> {
+			Some(())
+		}
+	}
+
+	#[test]
+	fn ignores_fingerprint_collisions() {
+		let mut countdown = Countdown {
+			value: 5,
+			received: Vec::new(),
+		};
+		assert_eq!(drive(&mut Echo, &mut countdown, None), Ok(Outcome::Halted { commands: 5 }));
+		assert_eq!(countdown.received.len(), 5);
+	}
+
+	// A world that no command changes.
+	struct Still;
+
+	impl Protocol for Still {
+		type Command = ();
+
+		type State = ();
+
+		const COMMAND_SIZE: usize = 2;
+
+		fn sense(&self) -> BigByte {
+			0
+		}
+
+		fn decode(&self, _: &[BigByte]) -> Result<(), &'static str> {
+			Ok(())
+		}
+
+		fn apply(&mut self, _: ()) {}
+
+		fn get_fingerprint(&self) -> Option<u64> {
+			Some(0)
+		}
+
+		fn get_state(&self) -> Option<()> {
+			Some(())
+		}
+	}
+
+	// Carries out one command, then two, and so on, whatever it reads.
+	struct Stutter {
+		double: bool,
+	}
+
+	impl Controller for Stutter {
+		type State = bool;
+
+		fn react(&mut self, _: BigByte) -> Result<Option<Vec<BigByte>>, &'static str> {
+			let outputs = vec![0; if self.double { 4 } else { 2 }];
+			self.double = !self.double;
+			Ok(Some(outputs))
+		}
+
+		fn get_fingerprint(&self) -> Option<u64> {
+			Some(0)
+		}
+
+		fn get_state(&self) -> Option<bool> {
+			Some(self.double)
+		}
+	}
+
+	#[test]
+	fn checks_cycles_skipped_over() {
+		// The states are only compared after the reaction jumping over the
+		// check, and match from the second command on.
+		let outcome = drive(&mut Stutter { double: false }, &mut Still, Some(100));
+		assert_eq!(outcome, Ok(Outcome::Cycle { entry: 1, length: 3 }));
+	}
+
+	#[test]
+	fn rejects_truncated_commands() {
+		let mut vm = create_program("3,100,4,100,99");
+		let mut countdown = Countdown {
+			value: 3,
+			received: Vec::new(),
+		};
+		assert!(drive(&mut vm, &mut countdown, None).is_err());
+	}
+}
@@ -0,0 +1,123 @@
+use crate::image;
+use crate::PanelColor;
+use common::grid::{Grid, Point, SparseGrid};
+use common::heading::{Heading, Turtle};
+
+/*
+ * Hull painted before the robot starts, its top left panel at 0,0. Text files
+ * draw white panels with `#` and black ones with `.`, and may place the robot
+ * with one of `^>v<` standing on a black panel. Images (pbm, ppm or png) have
+ * their dark pixels black and the others white.
+ */
+pub struct InitialHull {
+	pub name: String,
+	pub panels: SparseGrid<PanelColor>,
+	pub start: Option<Turtle>,
+}
+
+fn parse_text(text: &str) -> Result<(SparseGrid<PanelColor>, Option<Turtle>), String> {
+	let mut panels = SparseGrid::new();
+	let mut start = None;
+	for (y, line) in text.lines().enumerate() {
+		for (x, c) in line.trim_end().chars().enumerate() {
+			let position = Point::new(x as i32, y as i32);
+			let color = match c {
+				'#' => PanelColor::White,
+				'.' => PanelColor::Black,
+				_ => match Heading::from_arrow(c) {
+					Some(heading) if start.is_none() => {
+						start = Some(Turtle::new(position, heading));
+						PanelColor::Black
+					}
+					Some(_) => return Err(format!("Robot placed twice, again at {},{}", x, y)),
+					None => return Err(format!("Invalid panel '{}' at {},{}", c, x, y)),
+				},
+			};
+			panels.set(position, color);
+		}
+	}
+	Ok((panels, start))
+}
+
+fn from_image(image: &image::Image) -> SparseGrid<PanelColor> {
+	let mut panels = SparseGrid::new();
+	for (i, color) in image.pixels.iter().enumerate() {
+		let position = Point::new((i % image.width) as i32, (i / image.width) as i32);
+		panels.set(
+			position,
+			if image::is_dark(color) {
+				PanelColor::Black
+			} else {
+				PanelColor::White
+			},
+		);
+	}
+	panels
+}
+
+// The file extension tells images (pbm, ppm, png) from text.
+pub fn load_hull(filename: &str) -> Result<InitialHull, String> {
+	let path = std::path::Path::new(filename);
+	let name = path
+		.file_stem()
+		.map_or_else(|| filename.to_string(), |stem| stem.to_string_lossy().into_owned());
+	let extension = path.extension().map(|extension| extension.to_string_lossy().to_lowercase());
+	let (panels, start) = match extension.as_deref() {
+		Some("pbm") | Some("ppm") | Some("png") => (from_image(&image::read_image(filename)?), None),
+		_ => {
+			let text = std::fs::read_to_string(filename).map_err(|e| format!("Could not read {}: {}", filename, e))?;
+			parse_text(&text).map_err(|e| format!("Invalid hull {}: {}", filename, e))?
+		}
+	};
+	Ok(InitialHull { name, panels, start })
+}
+
+// Hull files of a directory, sorted by name.
+pub fn list_hulls(directory: &str) -> Result<Vec<String>, String> {
+	let entries = std::fs::read_dir(directory).map_err(|e| format!("Could not read {}: {}", directory, e))?;
+	let mut filenames: Vec<String> = entries
+		.filter_map(|entry| entry.ok())
+		.map(|entry| entry.path())
+		.filter(|path| {
+			let extension = path.extension().map(|extension| extension.to_string_lossy().to_lowercase());
+			matches!(extension.as_deref(), Some("txt") | Some("pbm") | Some("ppm") | Some("png"))
+		})
+		.map(|path| path.to_string_lossy().into_owned())
+		.collect();
+	filenames.sort();
+	Ok(filenames)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn parses_text_hulls() {
+		let (panels, start) = parse_text("#..\n.v#\n").unwrap();
+		assert_eq!(panels.len(), 6);
+		assert!(panels.get(Point::new(2, 1)) == Some(&PanelColor::White));
+		assert!(panels.get(Point::new(1, 1)) == Some(&PanelColor::Black));
+		assert_eq!(start, Some(Turtle::new(Point::new(1, 1), Heading::Down)));
+		assert!(parse_text("^>").is_err());
+		assert!(parse_text("#?").is_err());
+	}
+
+	#[test]
+	fn loads_png_hulls() {
+		let directory = std::env::temp_dir().join(format!("day11_png_hulls_{}", std::process::id()));
+		std::fs::create_dir_all(&directory).unwrap();
+		let filename = directory.join("hull.png").to_string_lossy().into_owned();
+		let mut picture = image::create_image(2, 1, [0, 0, 0]);
+		picture.set_pixel(1, 0, [255, 255, 255]);
+		image::write_image(&picture, &filename).unwrap();
+		let listed = list_hulls(&directory.to_string_lossy());
+		let hull = load_hull(&filename);
+		std::fs::remove_dir_all(&directory).unwrap();
+		assert_eq!(listed.unwrap(), vec![filename]);
+		let hull = hull.unwrap();
+		assert_eq!(hull.name, "hull");
+		assert!(hull.panels.get(Point::new(0, 0)) == Some(&PanelColor::Black));
+		assert!(hull.panels.get(Point::new(1, 0)) == Some(&PanelColor::White));
+	}
+}
@@ -0,0 +1,420 @@
+pub type Color = [u8; 3];
+
+pub struct Image {
+	pub width: usize,
+	pub height: usize,
+	pub pixels: Vec<Color>,
+}
+
+pub fn create_image(width: usize, height: usize, background: Color) -> Image {
+	Image {
+		width,
+		height,
+		pixels: vec![background; width * height],
+	}
+}
+
+// Rows of pixels, as rendered from a grid. Every row must have the same length.
+pub fn from_rows(rows: &[Vec<Color>]) -> Image {
+	Image {
+		width: rows.first().map_or(0, |row| row.len()),
+		height: rows.len(),
+		pixels: rows.concat(),
+	}
+}
+
+impl Image {
+	pub fn set_pixel(&mut self, x: usize, y: usize, color: Color) {
+		self.pixels[y * self.width + x] = color;
+	}
+
+	// Every pixel becomes a `scale` x `scale` square.
+	pub fn scaled(&self, scale: usize) -> Image {
+		let mut image = create_image(self.width * scale, self.height * scale, [0, 0, 0]);
+		for y in 0..image.height {
+			for x in 0..image.width {
+				image.pixels[y * image.width + x] = self.pixels[y / scale * self.width + x / scale];
+			}
+		}
+		image
+	}
+}
+
+pub fn parse_color(text: &str) -> Result<Color, &'static str> {
+	let text = text.trim_start_matches('#');
+	// Channels are sliced by byte, which only matches characters in ASCII.
+	if !text.is_ascii() || text.len() != 6 {
+		return Err("Invalid color, expected RRGGBB");
+	}
+	let mut color = [0; 3];
+	for (i, channel) in color.iter_mut().enumerate() {
+		*channel = u8::from_str_radix(&text[i * 2..i * 2 + 2], 16).map_err(|_| "Invalid color, expected RRGGBB")?;
+	}
+	Ok(color)
+}
+
+// PBM only knows black ink on white paper, dark pixels are inked.
+pub fn is_dark(color: &Color) -> bool {
+	let luminance = 299 * color[0] as u32 + 587 * color[1] as u32 + 114 * color[2] as u32;
+	luminance < 128 * 1000
+}
+
+fn get_pbm(image: &Image) -> Vec<u8> {
+	let mut data = format!("P4\n{} {}\n", image.width, image.height).into_bytes();
+	for row in image.pixels.chunks(image.width) {
+		for bits in row.chunks(8) {
+			let byte = bits
+				.iter()
+				.enumerate()
+				.filter(|(_, color)| is_dark(color))
+				.fold(0u8, |byte, (i, _)| byte | (0x80 >> i));
+			data.push(byte);
+		}
+	}
+	data
+}
+
+fn get_ppm(image: &Image) -> Vec<u8> {
+	let mut data = format!("P6\n{} {}\n255\n", image.width, image.height).into_bytes();
+	data.extend(image.pixels.iter().flat_map(|color| color.iter().cloned()));
+	data
+}
+
+fn get_crc(bytes: &[u8]) -> u32 {
+	let mut crc = 0xffff_ffffu32;
+	for byte in bytes {
+		crc ^= *byte as u32;
+		for _ in 0..8 {
+			crc = if crc & 1 == 1 { 0xedb8_8320 ^ (crc >> 1) } else { crc >> 1 };
+		}
+	}
+	!crc
+}
+
+fn get_adler(bytes: &[u8]) -> u32 {
+	let (mut a, mut b) = (1u32, 0u32);
+	for byte in bytes {
+		a = (a + *byte as u32) % 65521;
+		b = (b + a) % 65521;
+	}
+	(b << 16) | a
+}
+
+fn push_chunk(data: &mut Vec<u8>, kind: &[u8; 4], content: &[u8]) {
+	data.extend(&(content.len() as u32).to_be_bytes());
+	let start = data.len();
+	data.extend(kind);
+	data.extend(content);
+	let crc = get_crc(&data[start..]);
+	data.extend(&crc.to_be_bytes());
+}
+
+/*
+ * The pixels are stored in uncompressed deflate blocks: the files are bigger
+ * than they could be but no compression library is needed.
+ */
+fn get_png(image: &Image) -> Vec<u8> {
+	let mut raw = Vec::with_capacity(image.height * (image.width * 3 + 1));
+	for row in image.pixels.chunks(image.width) {
+		raw.push(0);
+		raw.extend(row.iter().flat_map(|color| color.iter().cloned()));
+	}
+	let mut zlib = vec![0x78, 0x01];
+	let blocks: Vec<&[u8]> = raw.chunks(0xffff).collect();
+	for (i, block) in blocks.iter().enumerate() {
+		zlib.push((i + 1 == blocks.len()) as u8);
+		zlib.extend(&(block.len() as u16).to_le_bytes());
+		zlib.extend(&(!(block.len() as u16)).to_le_bytes());
+		zlib.extend(*block);
+	}
+	zlib.extend(&get_adler(&raw).to_be_bytes());
+
+	let mut header = Vec::new();
+	header.extend(&(image.width as u32).to_be_bytes());
+	header.extend(&(image.height as u32).to_be_bytes());
+	header.extend(&[8, 2, 0, 0, 0]);
+
+	let mut data = vec![0x89, b'P', b'N', b'G', 0x0d, 0x0a, 0x1a, 0x0a];
+	push_chunk(&mut data, b"IHDR", &header);
+	push_chunk(&mut data, b"IDAT", &zlib);
+	push_chunk(&mut data, b"IEND", &[]);
+	data
+}
+
+// Whitespace separated header fields of a netpbm file, comments skipped.
+fn read_netpbm_fields(data: &[u8], count: usize) -> Result<(Vec<usize>, usize), String> {
+	let mut fields = Vec::new();
+	let mut position = 2;
+	while fields.len() < count {
+		match data.get(position) {
+			Some(b'#') => {
+				while data.get(position).is_some_and(|byte| *byte != b'\n') {
+					position += 1;
+				}
+			}
+			Some(byte) if byte.is_ascii_whitespace() => position += 1,
+			Some(byte) if byte.is_ascii_digit() => {
+				let start = position;
+				while data.get(position).is_some_and(|byte| byte.is_ascii_digit()) {
+					position += 1;
+				}
+				let field = std::str::from_utf8(&data[start..position]).unwrap();
+				fields.push(field.parse::<usize>().map_err(|_| "Invalid image header")?);
+			}
+			_ => return Err(String::from("Invalid image header")),
+		}
+	}
+	// A single whitespace separates the header from binary pixels.
+	Ok((fields, position + 1))
+}
+
+// Size in bytes of `height` rows of `row_size` bytes, None for empty or oversized images.
+fn get_data_size(width: usize, height: usize, row_size: usize) -> Option<usize> {
+	if width == 0 || height == 0 {
+		return None;
+	}
+	row_size.checked_mul(height)
+}
+
+fn read_u32(data: &[u8], position: usize) -> Option<u32> {
+	let bytes = data.get(position..position + 4)?;
+	Some(u32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+}
+
+/*
+ * Reads the PNG files `write_image` produces: 8 bit RGB, unfiltered rows and
+ * uncompressed deflate blocks. Anything else is rejected.
+ */
+fn read_png(data: &[u8]) -> Result<(usize, usize, Vec<Color>), &'static str> {
+	if data.get(0..8) != Some(&[0x89, b'P', b'N', b'G', 0x0d, 0x0a, 0x1a, 0x0a]) {
+		return Err("invalid png signature");
+	}
+	let mut header = None;
+	let mut zlib = Vec::new();
+	let mut position = 8;
+	loop {
+		let length = read_u32(data, position).ok_or("truncated chunk")? as usize;
+		let end = (position + 8).checked_add(length).ok_or("truncated chunk")?;
+		let chunk = data.get(position + 4..end).ok_or("truncated chunk")?;
+		if read_u32(data, end) != Some(get_crc(chunk)) {
+			return Err("corrupted chunk");
+		}
+		let (kind, content) = chunk.split_at(4);
+		match kind {
+			b"IHDR" if content.len() == 13 => {
+				if content[8..] != [8, 2, 0, 0, 0] {
+					return Err("only 8 bit RGB without interlacing is supported");
+				}
+				header = Some((read_u32(content, 0).unwrap() as usize, read_u32(content, 4).unwrap() as usize));
+			}
+			b"IDAT" => zlib.extend(content),
+			b"IEND" => break,
+			_ => (),
+		}
+		position = end + 4;
+	}
+	let (width, height) = header.ok_or("missing header")?;
+	let row_size = width.checked_mul(3).and_then(|size| size.checked_add(1)).ok_or("image too large")?;
+	let size = get_data_size(width, height, row_size).ok_or("empty or too large image")?;
+	let mut raw = Vec::with_capacity(size);
+	let mut position = 2;
+	loop {
+		let kind = *zlib.get(position).ok_or("truncated pixels")?;
+		if kind & 0b110 != 0 {
+			return Err("only uncompressed png is supported");
+		}
+		let lengths = zlib.get(position + 1..position + 5).ok_or("truncated pixels")?;
+		let length = u16::from_le_bytes([lengths[0], lengths[1]]);
+		if !length != u16::from_le_bytes([lengths[2], lengths[3]]) {
+			return Err("corrupted pixels");
+		}
+		position += 5;
+		raw.extend(zlib.get(position..position + length as usize).ok_or("truncated pixels")?);
+		position += length as usize;
+		if kind & 1 == 1 {
+			break;
+		}
+	}
+	if read_u32(&zlib, position) != Some(get_adler(&raw)) {
+		return Err("corrupted pixels");
+	}
+	if raw.len() != size {
+		return Err("truncated pixels");
+	}
+	let mut pixels = Vec::with_capacity(width * height);
+	for row in raw.chunks(row_size) {
+		if row[0] != 0 {
+			return Err("only unfiltered rows are supported");
+		}
+		pixels.extend(row[1..].chunks(3).map(|pixel| [pixel[0], pixel[1], pixel[2]]));
+	}
+	Ok((width, height, pixels))
+}
+
+// Reads binary PBM (P4), PPM (P6) and PNG files, the formats `write_image` produces.
+pub fn read_image(filename: &str) -> Result<Image, String> {
+	let data = std::fs::read(filename).map_err(|e| format!("Could not read {}: {}", filename, e))?;
+	let invalid = |reason: &str| format!("Invalid image {}: {}", filename, reason);
+	let (width, height, pixels) = match data.get(0..2) {
+		Some(b"P4") => {
+			let (fields, start) = read_netpbm_fields(&data, 2)?;
+			let (width, height) = (fields[0], fields[1]);
+			let row_size = width.div_ceil(8);
+			let size = get_data_size(width, height, row_size).ok_or_else(|| invalid("empty or too large image"))?;
+			let rows = data
+				.get(start..)
+				.and_then(|rest| rest.get(..size))
+				.ok_or_else(|| invalid("truncated pixels"))?;
+			let pixels = rows
+				.chunks(row_size)
+				.flat_map(|row| {
+					(0..width).map(move |x| {
+						if row[x / 8] & (0x80 >> (x % 8)) != 0 {
+							[0, 0, 0]
+						} else {
+							[255, 255, 255]
+						}
+					})
+				})
+				.collect();
+			(width, height, pixels)
+		}
+		Some(b"P6") => {
+			let (fields, start) = read_netpbm_fields(&data, 3)?;
+			if fields[2] != 255 {
+				return Err(invalid("only 8 bit channels are supported"));
+			}
+			let (width, height) = (fields[0], fields[1]);
+			let size = width
+				.checked_mul(3)
+				.and_then(|row_size| get_data_size(width, height, row_size))
+				.ok_or_else(|| invalid("empty or too large image"))?;
+			let bytes = data
+				.get(start..)
+				.and_then(|rest| rest.get(..size))
+				.ok_or_else(|| invalid("truncated pixels"))?;
+			(width, height, bytes.chunks(3).map(|pixel| [pixel[0], pixel[1], pixel[2]]).collect())
+		}
+		Some([0x89, b'P']) => read_png(&data).map_err(invalid)?,
+		_ => return Err(invalid("expected a binary pbm, ppm or png file")),
+	};
+	Ok(Image { width, height, pixels })
+}
+
+// The format is picked from the file extension: pbm, ppm or png.
+pub fn write_image(image: &Image, filename: &str) -> Result<(), String> {
+	let extension = std::path::Path::new(filename)
+		.extension()
+		.and_then(|extension| extension.to_str())
+		.map(|extension| extension.to_lowercase());
+	let data = match extension.as_deref() {
+		Some("pbm") => get_pbm(image),
+		Some("ppm") => get_ppm(image),
+		Some("png") => get_png(image),
+		_ => return Err(format!("Unsupported image format for {}, expected pbm, ppm or png", filename)),
+	};
+	std::fs::write(filename, data).map_err(|e| format!("Could not write {}: {}", filename, e))
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn parses_colors() {
+		assert_eq!(parse_color("#ff8000"), Ok([255, 128, 0]));
+		assert_eq!(parse_color("0a0B0c"), Ok([10, 11, 12]));
+		assert_eq!(parse_color("#ff80"), Err("Invalid color, expected RRGGBB"));
+		assert_eq!(parse_color("#ffgg00"), Err("Invalid color, expected RRGGBB"));
+		assert_eq!(parse_color("\u{e9}\u{e9}\u{e9}"), Err("Invalid color, expected RRGGBB"));
+	}
+
+	#[test]
+	fn packs_pbm_bits() {
+		// Rows are padded to whole bytes, the first pixel being the high bit.
+		let (black, white) = ([0, 0, 0], [255, 255, 255]);
+		let mut image = create_image(9, 2, white);
+		image.set_pixel(0, 0, black);
+		image.set_pixel(7, 0, black);
+		image.set_pixel(8, 0, black);
+		image.set_pixel(1, 1, [10, 10, 10]);
+		let mut expected = b"P4\n9 2\n".to_vec();
+		expected.extend(&[0b1000_0001, 0b1000_0000, 0b0100_0000, 0b0000_0000]);
+		assert_eq!(get_pbm(&image), expected);
+	}
+
+	#[test]
+	fn computes_checksums() {
+		assert_eq!(get_crc(b"IEND"), 0xae42_6082);
+		assert_eq!(get_crc(b"123456789"), 0xcbf4_3926);
+		assert_eq!(get_adler(b"Wikipedia"), 0x11e6_0398);
+		assert_eq!(get_adler(&[]), 1);
+	}
+
+	#[test]
+	fn lays_out_png_chunks() {
+		let mut data = Vec::new();
+		push_chunk(&mut data, b"IEND", &[]);
+		assert_eq!(data, [0, 0, 0, 0, b'I', b'E', b'N', b'D', 0xae, 0x42, 0x60, 0x82]);
+
+		let png = get_png(&create_image(1, 1, [1, 2, 3]));
+		assert_eq!(&png[..8], &[0x89, b'P', b'N', b'G', 0x0d, 0x0a, 0x1a, 0x0a]);
+		assert_eq!(&png[8..16], &[0, 0, 0, 13, b'I', b'H', b'D', b'R']);
+		assert_eq!(&png[16..29], &[0, 0, 0, 1, 0, 0, 0, 1, 8, 2, 0, 0, 0]);
+		// One final stored block holding the filter byte and the pixel.
+		let zlib = [0x78, 0x01, 1, 4, 0, 0xfb, 0xff, 0, 1, 2, 3];
+		assert_eq!(&png[33..37], &(zlib.len() as u32 + 4).to_be_bytes());
+		assert_eq!(&png[37..41], b"IDAT");
+		assert_eq!(&png[41..52], &zlib);
+		assert_eq!(&png[52..56], &get_adler(&[0, 1, 2, 3]).to_be_bytes());
+		assert_eq!(&png[png.len() - 12..], data.as_slice());
+	}
+
+	fn round_trip(image: &Image, extension: &str) -> Image {
+		let filename = std::env::temp_dir().join(format!("day11_round_trip_{}.{}", std::process::id(), extension));
+		let filename = filename.to_string_lossy();
+		write_image(image, &filename).unwrap();
+		let read = read_image(&filename);
+		std::fs::remove_file(&*filename).unwrap();
+		read.unwrap()
+	}
+
+	#[test]
+	fn reads_written_images() {
+		// 9 columns, so that PBM rows need padding.
+		let colors = [[0, 0, 0], [255, 255, 255], [12, 200, 7], [255, 0, 128]];
+		let pixels: Vec<Color> = (0..27).map(|i| colors[i * 7 % 4]).collect();
+		let image = Image { width: 9, height: 3, pixels };
+		for extension in ["ppm", "png"].iter() {
+			let read = round_trip(&image, extension);
+			assert_eq!((read.width, read.height, read.pixels), (9, 3, image.pixels.clone()), "{}", extension);
+		}
+		// PBM only keeps black and white.
+		let black_and_white: Vec<Color> = image
+			.pixels
+			.iter()
+			.map(|color| if is_dark(color) { [0, 0, 0] } else { [255, 255, 255] })
+			.collect();
+		let read = round_trip(&image, "pbm");
+		assert_eq!((read.width, read.height, read.pixels), (9, 3, black_and_white));
+	}
+
+	#[test]
+	fn rejects_invalid_sizes() {
+		let read = |name: &str, data: &[u8]| {
+			let filename = std::env::temp_dir().join(format!("day11_{}_{}", std::process::id(), name));
+			std::fs::write(&filename, data).unwrap();
+			let read = read_image(&filename.to_string_lossy()).map(|_| ());
+			std::fs::remove_file(&filename).unwrap();
+			read.map_err(|e| e.rsplit(": ").next().unwrap().to_string())
+		};
+		assert_eq!(read("empty.pbm", b"P4\n0 1\n"), Err(String::from("empty or too large image")));
+		assert_eq!(read("empty.ppm", b"P6\n3 0\n255\n"), Err(String::from("empty or too large image")));
+		let huge = format!("P6\n{} 2\n255\n", usize::MAX / 4);
+		assert_eq!(read("huge.ppm", huge.as_bytes()), Err(String::from("empty or too large image")));
+		assert_eq!(read("short.ppm", b"P6\n1 1\n255"), Err(String::from("truncated pixels")));
+		let mut png = get_png(&create_image(1, 1, [1, 2, 3]));
+		png[41] = 0b10;
+		assert_eq!(read("corrupted.png", &png), Err(String::from("corrupted chunk")));
+	}
+}
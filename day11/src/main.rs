@@ -1,4 +1,20 @@
-mod vm;
+mod analysis;
+mod dot;
+mod driver;
+mod hull;
+mod image;
+mod replay;
+mod rules;
+mod stats;
+
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashSet;
+use std::hash::{Hash, Hasher};
+
+use common::grid::{Bounds, Grid, Point, SparseGrid};
+use common::heading::{get_heading, Heading, Turn, Turtle};
+use common::svg::{self, Svg};
+use common::vm;
 
 #[derive(Clone, PartialEq)]
 enum PanelColor {
@@ -6,145 +22,220 @@ enum PanelColor {
   White = 1,
 }
 
-enum Turn {
-  Left = 0,
-  Right = 1,
+struct Order {
+  panel_color: PanelColor,
+  turn: Turn,
 }
 
-struct Direction {
-  x: i32,
-  y: i32,
+// One order carried out by the robot, as seen from the panel it stood on.
+struct Step {
+  position: Point,
+  heading: Heading,
+  read: PanelColor,
+  painted: PanelColor,
 }
 
-#[derive(Hash, Clone)]
-struct Coordinates {
-  x: i32,
-  y: i32,
+struct Robot {
+  turtle: Turtle,
+  hull: SparseGrid<PanelColor>,
+  stats: stats::Statistics,
+  history: Vec<Step>,
+  // Sum of the hashes of the white panels, kept up to date by `apply`.
+  hull_fingerprint: u64,
 }
 
-impl std::cmp::PartialEq for Coordinates {
-  fn eq(&self, other: &Self) -> bool {
-    self.x == other.x && self.y == other.y
-  }
+fn get_panel_hash(position: Point) -> u64 {
+  let mut hasher = DefaultHasher::new();
+  position.hash(&mut hasher);
+  hasher.finish()
 }
 
-impl std::cmp::Eq for Coordinates {}
-
-struct Order {
-  panel_color: PanelColor,
-  turn: Turn,
+// Only white panels count, black ones read the same as panels never painted.
+fn get_hull_fingerprint(hull: &SparseGrid<PanelColor>) -> u64 {
+  hull
+    .iter()
+    .filter(|(_, color)| **color == PanelColor::White)
+    .map(|(position, _)| get_panel_hash(*position))
+    .fold(0u64, u64::wrapping_add)
 }
 
-struct Robot {
-  direction: Direction,
-  position: Coordinates,
-  known_panels: std::collections::HashMap<Coordinates, PanelColor>,
-  top_left: Coordinates,
-  bottom_right: Coordinates,
-  painted_panels: usize,
-  vm: vm::VirtualMachine,
+impl driver::Protocol for Robot {
+  type Command = Order;
+
+  // The robot and its white panels, as the fingerprint sees them.
+  type State = (Turtle, HashSet<Point>);
+
+  const COMMAND_SIZE: usize = 2;
+
+  fn sense(&self) -> vm::BigByte {
+    self.get_panel(self.turtle.position) as vm::BigByte
+  }
+
+  fn decode(&self, outputs: &[vm::BigByte]) -> Result<Order, &'static str> {
+    Ok(Order {
+      panel_color: get_panel_color(outputs[0])?,
+      turn: get_turn(outputs[1])?,
+    })
+  }
+
+  fn apply(&mut self, order: Order) {
+    self.history.push(Step {
+      position: self.turtle.position,
+      heading: self.turtle.heading,
+      read: self.get_panel(self.turtle.position),
+      painted: order.panel_color.clone(),
+    });
+    let flipped = self.get_panel(self.turtle.position) != order.panel_color;
+    if flipped {
+      let hash = get_panel_hash(self.turtle.position);
+      self.hull_fingerprint = match order.panel_color {
+        PanelColor::White => self.hull_fingerprint.wrapping_add(hash),
+        PanelColor::Black => self.hull_fingerprint.wrapping_sub(hash),
+      };
+    }
+    stats::record_paint(&mut self.stats, self.turtle.position, flipped);
+    self.hull.set(self.turtle.position, order.panel_color);
+    self.turtle.turn(order.turn);
+    self.turtle.forward();
+    stats::record_visit(&mut self.stats, self.turtle.position);
+  }
+
+  fn get_fingerprint(&self) -> Option<u64> {
+    let mut hasher = DefaultHasher::new();
+    (self.hull_fingerprint, self.turtle).hash(&mut hasher);
+    Some(hasher.finish())
+  }
+
+  fn get_state(&self) -> Option<Self::State> {
+    let white = self
+      .hull
+      .iter()
+      .filter(|(_, color)| **color == PanelColor::White)
+      .map(|(position, _)| *position)
+      .collect();
+    Some((self.turtle, white))
+  }
 }
 
 impl Robot {
-  fn paint_panel(&mut self, panel_color: &PanelColor) {
-    self
-      .known_panels
-      .insert(self.position.clone(), panel_color.clone());
+  fn get_panel(&self, position: Point) -> PanelColor {
+    self.hull.get(position).cloned().unwrap_or(PanelColor::Black)
   }
 
-  fn execute(&mut self, order: &Order) -> vm::BigByte {
-    if self.top_left.x > self.position.x {
-      self.top_left.x = self.position.x;
-    }
-    if self.top_left.y > self.position.y {
-      self.top_left.y = self.position.y;
-    }
-    if self.bottom_right.x < self.position.x {
-      self.bottom_right.x = self.position.x;
-    }
-    if self.bottom_right.y < self.position.y {
-      self.bottom_right.y = self.position.y;
-    }
-    self.paint_panel(&order.panel_color);
-    self.painted_panels = self.known_panels.len();
-    self.next_panel(&order.turn)
-  }
-
-  fn run(&mut self, start_color: PanelColor) {
-    let mut next_input = start_color as vm::BigByte;
-    while self.vm.is_running() {
-      self.vm.push_input(next_input);
-      self.vm.run().expect("VM run failed");
-      if self.vm.is_running() {
-        let order = get_order(&mut self.vm).expect("Malformed orders");
-        next_input = self.execute(&order);
-      }
-    }
+  fn control<C: driver::Controller>(
+    &mut self,
+    controller: &mut C,
+    limit: Option<usize>,
+  ) -> Result<driver::Outcome, &'static str> {
+    driver::drive(controller, self, limit)
+  }
+
+  fn run(&mut self, memory: &vm::Memory, options: &Options) -> Result<driver::Outcome, &'static str> {
+    let mut vm = vm::create_vm(memory.clone(), options.tier);
+    self.control(&mut vm, options.limit)
   }
 
   fn number_of_known_panels(&self) -> usize {
-    self.painted_panels
+    self.stats.iter().filter(|(_, panel)| panel.painted > 0).count()
   }
 
-  fn next_panel(&mut self, turn: &Turn) -> vm::BigByte {
-    self.rotate(turn);
-    self.position.x += self.direction.x;
-    self.position.y += self.direction.y;
-    self
-      .known_panels
-      .entry(self.position.clone())
-      .or_insert(PanelColor::Black)
-      .clone() as vm::BigByte
-  }
-
-  fn rotate(&mut self, turn: &Turn) {
-    match turn {
-      Turn::Left => {
-        self.direction = Direction {
-          x: self.direction.y,
-          y: -self.direction.x,
-        }
-      }
-      Turn::Right => {
-        self.direction = Direction {
-          x: -self.direction.y,
-          y: self.direction.x,
-        }
-      }
-    }
+  fn get_bounds(&self) -> Bounds {
+    self.hull.bounds().unwrap_or_else(|| Bounds::from_point(self.turtle.position))
   }
 
   fn draw(&self) {
-    let line_size = (self.bottom_right.x - self.top_left.x) as usize;
-    let mut index = Coordinates { x: 0, y: 0 };
-    for y in self.top_left.y..self.bottom_right.y + 1 {
-      let mut line = vec!['.'; line_size];
-      index.y = y;
-      for (i, x) in (self.top_left.x..self.bottom_right.x + 1).enumerate() {
-        index.x = x;
-        if self.known_panels.contains_key(&index) && self.known_panels[&index] == PanelColor::White
-        {
-          line[i] = '#';
-        }
-      }
-      let line_str: String = line.into_iter().collect();
-      println!("{}", line_str);
-    }
+    println!(
+      "{}",
+      self.hull.render_text(|panel| match panel {
+        Some(PanelColor::White) => '#',
+        _ => '.',
+      })
+    );
   }
+
+  fn get_grid(&self) -> Vec<Vec<bool>> {
+    self
+      .hull
+      .render(&self.get_bounds(), |_, panel| panel == Some(&PanelColor::White))
+  }
+
+  fn get_image(&self, white: image::Color, black: image::Color) -> image::Image {
+    get_hull_image(&self.hull, &self.get_bounds(), white, black)
+  }
+
+  // The hull with the path of the robot through the centers of the panels.
+  fn get_svg(&self, white: image::Color, black: image::Color) -> Svg {
+    let mut bounds = self.get_bounds();
+    bounds.extend(self.turtle.position);
+    let mut svg = Svg::new();
+    svg.add_rect(
+      bounds.top_left.x as f64,
+      bounds.top_left.y as f64,
+      bounds.width() as f64,
+      bounds.height() as f64,
+      &svg::get_color(black),
+    );
+    let white = svg::get_color(white);
+    svg.add_cells(&self.hull, &bounds, |panel| match panel {
+      Some(PanelColor::White) => Some(white.clone()),
+      _ => None,
+    });
+    let trajectory: Vec<(f64, f64)> = self
+      .history
+      .iter()
+      .map(|step| step.position)
+      .chain(std::iter::once(self.turtle.position))
+      .map(|position| (position.x as f64 + 0.5, position.y as f64 + 0.5))
+      .collect();
+    svg.add_polyline(&trajectory, "#ff0000", 1.5);
+    svg
+  }
+}
+
+fn get_hull_image(
+  hull: &SparseGrid<PanelColor>,
+  bounds: &Bounds,
+  white: image::Color,
+  black: image::Color,
+) -> image::Image {
+  let rows = hull.render(bounds, |_, panel| match panel {
+    Some(PanelColor::White) => white,
+    _ => black,
+  });
+  image::from_rows(&rows)
 }
 
-fn create_robot(memory: &vm::Memory) -> Robot {
+fn create_robot(hull: SparseGrid<PanelColor>, turtle: Turtle) -> Robot {
+  let mut stats = SparseGrid::new();
+  stats::record_visit(&mut stats, turtle.position);
   Robot {
-    direction: Direction { x: 0, y: -1 },
-    position: Coordinates { x: 0, y: 0 },
-    known_panels: std::collections::HashMap::new(),
-    top_left: Coordinates { x: 0, y: 0 },
-    bottom_right: Coordinates { x: 0, y: 0 },
-    painted_panels: 0,
-    vm: vm::create_vm(memory.clone()),
+    turtle,
+    hull_fingerprint: get_hull_fingerprint(&hull),
+    hull,
+    stats,
+    history: Vec::new(),
   }
 }
 
+// The robot alone at the origin, on a panel of the given color.
+fn create_lone_robot(start_color: PanelColor) -> Robot {
+  let mut hull = SparseGrid::new();
+  hull.set(Point::new(0, 0), start_color);
+  create_robot(hull, Turtle::new(Point::new(0, 0), Heading::Up))
+}
+
+// The start options override the robot placed in the hull, which defaults to 0,0 heading up.
+fn create_robot_on(initial: &hull::InitialHull, options: &Options) -> Robot {
+  let start = initial
+    .start
+    .unwrap_or_else(|| Turtle::new(Point::new(0, 0), Heading::Up));
+  let turtle = Turtle::new(
+    options.start.unwrap_or(start.position),
+    options.heading.unwrap_or(start.heading),
+  );
+  create_robot(initial.panels.clone(), turtle)
+}
+
 fn get_panel_color(value: vm::BigByte) -> Result<PanelColor, &'static str> {
   match value {
     0 => Ok(PanelColor::Black),
@@ -153,7 +244,7 @@ fn get_panel_color(value: vm::BigByte) -> Result<PanelColor, &'static str> {
   }
 }
 
-fn get_direction(value: vm::BigByte) -> Result<Turn, &'static str> {
+fn get_turn(value: vm::BigByte) -> Result<Turn, &'static str> {
   match value {
     0 => Ok(Turn::Left),
     1 => Ok(Turn::Right),
@@ -161,34 +252,299 @@ fn get_direction(value: vm::BigByte) -> Result<Turn, &'static str> {
   }
 }
 
-fn get_order(vm: &mut vm::VirtualMachine) -> Result<Order, &'static str> {
-  Ok(Order {
-    panel_color: match vm.pop_output() {
-      Some(output) => get_panel_color(output)?,
-      None => return Err("Expected an output for PanelColor but none were found"),
-    },
-    turn: match vm.pop_output() {
-      Some(output) => get_direction(output)?,
-      None => return Err("Expected an output for Direction but none were found"),
-    },
-  })
+// Rule tables like Langton's ant may never stop nor repeat, so experiments
+// with --rules are cut after this many orders unless --limit says otherwise.
+const RULES_LIMIT: usize = 20_000;
+
+struct Options {
+  tier: vm::Tier,
+  analyze: bool,
+  dot: Option<String>,
+  image: Option<String>,
+  scale: usize,
+  white: image::Color,
+  black: image::Color,
+  replay: bool,
+  speed: u32,
+  frames: Option<String>,
+  hulls: Vec<String>,
+  start: Option<Point>,
+  heading: Option<Heading>,
+  output: Option<String>,
+  csv: Option<String>,
+  heatmap: Option<String>,
+  rules: Option<String>,
+  limit: Option<usize>,
+  svg: Option<String>,
 }
 
-fn part1(memory: &vm::Memory) -> () {
+fn get_point(text: &str) -> Result<Point, &'static str> {
+  let mut coordinates = text.split(',').map(|value| value.trim().parse::<i32>());
+  match (coordinates.next(), coordinates.next(), coordinates.next()) {
+    (Some(Ok(x)), Some(Ok(y)), None) => Ok(Point::new(x, y)),
+    _ => Err("Invalid position, expected X,Y"),
+  }
+}
+
+fn parse_options(args: &[String]) -> Result<Options, String> {
+  let mut options = Options {
+    tier: vm::Tier::Interpreter,
+    analyze: false,
+    dot: None,
+    image: None,
+    scale: 1,
+    white: [255, 255, 255],
+    black: [0, 0, 0],
+    replay: false,
+    speed: 60,
+    frames: None,
+    hulls: Vec::new(),
+    start: None,
+    heading: None,
+    output: None,
+    csv: None,
+    heatmap: None,
+    rules: None,
+    limit: None,
+    svg: None,
+  };
+  let mut it = args.iter();
+  while let Some(arg) = it.next() {
+    match arg.as_str() {
+      "--tier" => options.tier = vm::get_tier(it.next().ok_or("Missing value after --tier")?)?,
+      "--analyze" => options.analyze = true,
+      "--dot" => options.dot = Some(it.next().ok_or("Missing value after --dot")?.clone()),
+      "--image" => options.image = Some(it.next().ok_or("Missing value after --image")?.clone()),
+      "--scale" => {
+        options.scale = it
+          .next()
+          .ok_or("Missing value after --scale")?
+          .parse::<usize>()
+          .map_err(|_| "Invalid scale")?;
+        if options.scale == 0 {
+          return Err(String::from("Scale must be at least 1"));
+        }
+      }
+      "--white" => options.white = image::parse_color(it.next().ok_or("Missing value after --white")?)?,
+      "--replay" => options.replay = true,
+      "--speed" => {
+        options.speed = it
+          .next()
+          .ok_or("Missing value after --speed")?
+          .parse::<u32>()
+          .map_err(|_| "Invalid speed")?
+      }
+      "--frames" => options.frames = Some(it.next().ok_or("Missing value after --frames")?.clone()),
+      "--black" => options.black = image::parse_color(it.next().ok_or("Missing value after --black")?)?,
+      "--hull" => options.hulls.push(it.next().ok_or("Missing value after --hull")?.clone()),
+      "--batch" => {
+        let directory = it.next().ok_or("Missing value after --batch")?;
+        options.hulls.extend(hull::list_hulls(directory)?);
+      }
+      "--start" => options.start = Some(get_point(it.next().ok_or("Missing value after --start")?)?),
+      "--heading" => options.heading = Some(get_heading(it.next().ok_or("Missing value after --heading")?)?),
+      "--output" => options.output = Some(it.next().ok_or("Missing value after --output")?.clone()),
+      "--csv" => options.csv = Some(it.next().ok_or("Missing value after --csv")?.clone()),
+      "--heatmap" => options.heatmap = Some(it.next().ok_or("Missing value after --heatmap")?.clone()),
+      "--svg" => options.svg = Some(it.next().ok_or("Missing value after --svg")?.clone()),
+      "--rules" => options.rules = Some(it.next().ok_or("Missing value after --rules")?.clone()),
+      "--limit" => {
+        options.limit = Some(
+          it.next()
+            .ok_or("Missing value after --limit")?
+            .parse::<usize>()
+            .map_err(|_| "Invalid limit")?,
+        )
+      }
+      _ => return Err(String::from("Unknown option")),
+    }
+  }
+  Ok(options)
+}
+
+fn print_edge(edge: &analysis::Edge) -> String {
+  match edge {
+    analysis::Edge::Next(address) => format!("{}", address),
+    analysis::Edge::Jump(address) => format!("jump {}", address),
+    analysis::Edge::Return(address) => format!("return {}", address),
+    analysis::Edge::Indirect => String::from("indirect"),
+  }
+}
+
+fn print_analysis(analysis: &analysis::Analysis) {
+  println!("Analysis: {} basic blocks", analysis.blocks.len());
+  for block in &analysis.blocks {
+    let edges: Vec<String> = block.edges.iter().map(print_edge).collect();
+    println!(
+      "  {}..{} -> [{}]",
+      block.addresses[0],
+      block.addresses[block.addresses.len() - 1],
+      edges.join(", ")
+    );
+  }
+  for problem in &analysis.problems {
+    println!("  {}", problem);
+  }
+}
+
+// Only --analyze rejects a program, the analysis being too coarse to stop a run on its own.
+fn check_program(memory: &vm::Memory, options: &Options) -> Result<(), String> {
+  if !options.analyze && options.dot.is_none() {
+    return Ok(());
+  }
+  let analysis = analysis::analyze(memory);
+  if options.analyze {
+    print_analysis(&analysis);
+  }
+  if let Some(filename) = &options.dot {
+    dot::write_dot(&analysis, filename).map_err(|e| format!("Could not write {}: {}", filename, e))?;
+    println!("Control flow graph written to {}", filename);
+  }
+  let errors: Vec<String> = analysis
+    .problems
+    .iter()
+    .filter(|problem| problem.is_error())
+    .map(|problem| problem.to_string())
+    .collect();
+  if options.analyze && !errors.is_empty() {
+    return Err(format!("Invalid program:\n{}", errors.join("\n")));
+  }
+  Ok(())
+}
+
+fn print_outcome(part: &str, outcome: &driver::Outcome) {
+  match outcome {
+    driver::Outcome::Cycle { entry, length } => println!(
+      "{}: the robot would run forever, repeating {} orders from order {}",
+      part, length, entry
+    ),
+    driver::Outcome::Interrupted { commands } => {
+      println!("{}: the robot was interrupted after {} orders", part, commands)
+    }
+    driver::Outcome::Halted { .. } => (),
+  }
+}
+
+fn part1(memory: &vm::Memory, options: &Options) {
   let before = std::time::Instant::now();
-  let mut robot = create_robot(memory);
-  robot.run(PanelColor::Black);
+  let mut robot = create_lone_robot(PanelColor::Black);
+  let outcome = robot.run(memory, options).expect("Robot program failed");
   println!("Part1: Elapsed time: {:.2?}", before.elapsed());
+  print_outcome("Part1", &outcome);
   println!("Part1: result: {}", robot.number_of_known_panels());
+  println!("Part1: statistics: {}", stats::summarize(&robot.stats));
 }
 
-fn part2(memory: &vm::Memory) -> () {
+fn part2(memory: &vm::Memory, options: &Options) {
   let before = std::time::Instant::now();
-  let mut robot = create_robot(memory);
-  robot.run(PanelColor::White);
+  let mut robot = create_lone_robot(PanelColor::White);
+  let outcome = robot.run(memory, options).expect("Robot program failed");
   println!("Part2: Elapsed time: {:.2?}", before.elapsed());
+  print_outcome("Part2", &outcome);
   println!("Part2: result:");
   robot.draw();
+  match common::ocr::recognize(&robot.get_grid()) {
+    Ok(text) => println!("Part2: registration identifier: {}", text),
+    Err(e) => println!("Part2: could not read the registration identifier: {}", e),
+  }
+  if let Some(filename) = &options.image {
+    let image = robot.get_image(options.white, options.black).scaled(options.scale);
+    image::write_image(&image, filename).expect("Could not export the hull");
+    println!("Part2: hull written to {}", filename);
+  }
+  if let Some(filename) = &options.svg {
+    let svg = robot.get_svg(options.white, options.black);
+    svg::write_svg(&svg, 1000, filename).expect("Could not export the hull");
+    println!("Part2: hull and trajectory written to {}", filename);
+  }
+  println!("Part2: statistics: {}", stats::summarize(&robot.stats));
+  if let Some(filename) = &options.csv {
+    stats::write_csv(&robot.stats, &robot.hull, filename).expect("Could not export the statistics");
+    println!("Part2: statistics written to {}", filename);
+  }
+  if let Some(filename) = &options.heatmap {
+    let heatmap = stats::get_heatmap(&robot.stats, &robot.stats.bounds().unwrap()).scaled(options.scale);
+    image::write_image(&heatmap, filename).expect("Could not export the heatmap");
+    println!("Part2: heatmap written to {}", filename);
+  }
+  if let Some(directory) = &options.frames {
+    let count = replay::write_frames(&robot.history, directory, options.scale, options.white, options.black)
+      .expect("Could not export the frames");
+    println!("Part2: {} frames written to {}", count, directory);
+  }
+  if options.replay {
+    replay::play(&robot.history, options.speed);
+  }
+}
+
+fn run_experiment(memory: &vm::Memory, options: &Options, initial: &hull::InitialHull) -> Result<String, String> {
+  let mut robot = create_robot_on(initial, options);
+  let outcome = match &options.rules {
+    Some(filename) => {
+      let limit = options.limit.unwrap_or(RULES_LIMIT);
+      robot.control(&mut rules::load_rules(filename)?, Some(limit))?
+    }
+    None => robot.run(memory, options)?,
+  };
+  let mut report = format!("{}: {}", initial.name, stats::summarize(&robot.stats));
+  match outcome {
+    driver::Outcome::Cycle { entry, length } => {
+      report += &format!(", cycles every {} orders from order {}", length, entry)
+    }
+    driver::Outcome::Interrupted { commands } => report += &format!(", interrupted after {} orders", commands),
+    driver::Outcome::Halted { .. } => (),
+  }
+  match &options.output {
+    Some(directory) => {
+      std::fs::create_dir_all(directory).map_err(|e| format!("Could not create {}: {}", directory, e))?;
+      let path = std::path::Path::new(directory).join(format!("{}.png", initial.name));
+      let image = robot.get_image(options.white, options.black).scaled(options.scale);
+      image::write_image(&image, &path.to_string_lossy())?;
+      report += &format!(", final hull written to {}", path.display());
+    }
+    None => {
+      report += &format!(
+        ", final hull:\n{}",
+        robot.hull.render_text(|panel| match panel {
+          Some(PanelColor::White) => '#',
+          _ => '.',
+        })
+      );
+    }
+  }
+  Ok(report)
+}
+
+/*
+ * One run per initial hull, a failing one does not stop the others. Rules
+ * given without any hull run on an empty one.
+ */
+fn experiments(memory: &vm::Memory, options: &Options) {
+  if options.hulls.is_empty() && options.rules.is_none() {
+    return;
+  }
+  let before = std::time::Instant::now();
+  if options.hulls.is_empty() {
+    println!("Experiments: rules on an empty hull");
+    let empty = hull::InitialHull {
+      name: String::from("empty"),
+      panels: SparseGrid::new(),
+      start: None,
+    };
+    match run_experiment(memory, options, &empty) {
+      Ok(report) => println!("  {}", report),
+      Err(e) => println!("  {}: failed: {}", empty.name, e),
+    }
+  } else {
+    println!("Experiments: {} initial hulls", options.hulls.len());
+  }
+  for filename in &options.hulls {
+    match hull::load_hull(filename).and_then(|initial| run_experiment(memory, options, &initial)) {
+      Ok(report) => println!("  {}", report),
+      Err(e) => println!("  {}: failed: {}", filename, e),
+    }
+  }
+  println!("Experiments: Elapsed time: {:.2?}", before.elapsed());
 }
 
 fn main() {
@@ -199,8 +555,79 @@ fn main() {
   }
   let filename = &args[1];
   println!("Loading file {}", filename);
+  let options = parse_options(&args[2..]).expect("Invalid arguments");
   let memory = vm::parse_memory_file(filename);
-  part1(&memory);
-  part2(&memory);
+  if let Err(e) = check_program(&memory, &options) {
+    panic!("{}", e);
+  }
+  part1(&memory, &options);
+  part2(&memory, &options);
+  experiments(&memory, &options);
   println!("Total elapsed time: {:.2?}", before.elapsed());
 }
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn detects_robots_going_in_circles() {
+    // Paints white and turns right whatever it reads, forever.
+    let memory = vm::parse_memory("3,100,104,1,104,1,1105,1,0");
+    let mut robot = create_robot(SparseGrid::new(), Turtle::new(Point::new(0, 0), Heading::Up));
+    let mut vm = vm::create_vm(memory, vm::Tier::Interpreter);
+    let outcome = robot.control(&mut vm, None);
+    // The last read cell only holds white from the fifth order on.
+    assert_eq!(outcome, Ok(driver::Outcome::Cycle { entry: 5, length: 4 }));
+    assert_eq!(robot.number_of_known_panels(), 4);
+  }
+
+  fn render(robot: &Robot) -> String {
+    robot.hull.render_text(|panel| match panel {
+      Some(PanelColor::White) => '#',
+      _ => '.',
+    })
+  }
+
+  #[test]
+  fn follows_scripted_orders() {
+    let mut orders = 0;
+    // Paints white and turns left, three times.
+    let mut script = |_| {
+      orders += 1;
+      if orders > 3 {
+        None
+      } else {
+        Some(vec![1, 0])
+      }
+    };
+    let mut robot = create_robot(SparseGrid::new(), Turtle::new(Point::new(0, 0), Heading::Up));
+    let outcome = robot.control(&mut script, None);
+    assert_eq!(outcome, Ok(driver::Outcome::Halted { commands: 3 }));
+    assert_eq!(render(&robot), "##\n#.");
+    assert_eq!(robot.turtle, Turtle::new(Point::new(0, 1), Heading::Right));
+    assert_eq!(robot.number_of_known_panels(), 3);
+  }
+
+  #[test]
+  fn walks_like_langtons_ant() {
+    let mut ant = rules::parse_rules(include_str!("../langton.txt")).unwrap();
+    let mut robot = create_robot(SparseGrid::new(), Turtle::new(Point::new(0, 0), Heading::Up));
+    let outcome = robot.control(&mut ant, Some(5));
+    assert_eq!(outcome, Ok(driver::Outcome::Interrupted { commands: 5 }));
+    assert_eq!(render(&robot), ".#\n##");
+    assert_eq!(robot.turtle, Turtle::new(Point::new(-1, 0), Heading::Left));
+  }
+
+  #[test]
+  fn limits_rule_runs() {
+    let options = parse_options(&[String::from("--rules"), String::from("langton.txt")]).unwrap();
+    let empty = hull::InitialHull {
+      name: String::from("empty"),
+      panels: SparseGrid::new(),
+      start: None,
+    };
+    let report = run_experiment(&vm::Memory::new(), &options, &empty).unwrap();
+    assert!(report.contains(&format!("interrupted after {} orders", RULES_LIMIT)));
+  }
+}
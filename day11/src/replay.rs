@@ -0,0 +1,143 @@
+use crate::image;
+use crate::{get_hull_image, PanelColor, Step};
+use common::grid::{Bounds, Grid, Point, SparseGrid};
+
+const ROBOT_COLOR: image::Color = [255, 0, 0];
+
+/*
+ * Hull as it was before a given step, with the robot standing on the panel of
+ * that step. Past the last step the robot is gone and the hull is final.
+ */
+struct Frame<'a> {
+	history: &'a [Step],
+	hull: SparseGrid<PanelColor>,
+	step: usize,
+	bounds: Bounds,
+}
+
+fn create_frame(history: &[Step]) -> Frame<'_> {
+	let mut bounds = Bounds::from_point(Point::new(0, 0));
+	for step in history {
+		bounds.extend(step.position);
+	}
+	let mut hull = SparseGrid::new();
+	if let Some(first) = history.first() {
+		hull.set(first.position, first.read.clone());
+	}
+	Frame {
+		history,
+		hull,
+		step: 0,
+		bounds,
+	}
+}
+
+impl<'a> Frame<'a> {
+	fn advance(&mut self) -> bool {
+		match self.history.get(self.step) {
+			Some(step) => {
+				self.hull.set(step.position, step.painted.clone());
+				self.step += 1;
+				true
+			}
+			None => false,
+		}
+	}
+
+	fn get_robot(&self) -> Option<&Step> {
+		self.history.get(self.step)
+	}
+
+	fn get_text(&self) -> String {
+		let robot = self.get_robot();
+		let rows = self.hull.render(&self.bounds, |position, panel| match (robot, panel) {
+			(Some(step), _) if step.position == position => step.heading.arrow(),
+			(_, Some(PanelColor::White)) => '#',
+			_ => '.',
+		});
+		rows.iter().map(|row| row.iter().collect::<String>() + "\n").collect()
+	}
+
+	fn get_image(&self, white: image::Color, black: image::Color) -> image::Image {
+		let mut image = get_hull_image(&self.hull, &self.bounds, white, black);
+		if let Some(step) = self.get_robot() {
+			let (x, y) = self.bounds.offset(step.position);
+			image.set_pixel(x, y, ROBOT_COLOR);
+		}
+		image
+	}
+}
+
+// Plays the history back in the terminal, `speed` being in steps per second.
+pub fn play(history: &[Step], speed: u32) {
+	let delay = std::time::Duration::from_secs(1) / speed.max(1);
+	let mut frame = create_frame(history);
+	print!("\x1b[2J");
+	loop {
+		print!("\x1b[H{}", frame.get_text());
+		println!("step {}/{}\x1b[K", frame.step, history.len());
+		if !frame.advance() {
+			break;
+		}
+		std::thread::sleep(delay);
+	}
+}
+
+// Writes one numbered image per step in `directory`, returns how many were written.
+pub fn write_frames(
+	history: &[Step],
+	directory: &str,
+	scale: usize,
+	white: image::Color,
+	black: image::Color,
+) -> Result<usize, String> {
+	std::fs::create_dir_all(directory).map_err(|e| format!("Could not create {}: {}", directory, e))?;
+	let mut frame = create_frame(history);
+	let mut count = 0;
+	loop {
+		let filename = std::path::Path::new(directory).join(format!("frame_{:05}.ppm", count));
+		let image = frame.get_image(white, black).scaled(scale);
+		image::write_image(&image, &filename.to_string_lossy())?;
+		count += 1;
+		if !frame.advance() {
+			return Ok(count);
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::{create_lone_robot, Robot};
+
+	// Paints white and turns right twice, then paints black and turns right.
+	fn get_robot() -> Robot {
+		let mut orders = vec![vec![0, 1], vec![1, 1], vec![1, 1]];
+		let mut script = |_| orders.pop();
+		let mut robot = create_lone_robot(PanelColor::Black);
+		robot.control(&mut script, None).unwrap();
+		robot
+	}
+
+	#[test]
+	fn replays_every_step() {
+		let robot = get_robot();
+		let mut frame = create_frame(&robot.history);
+		let mut texts = vec![frame.get_text()];
+		while frame.advance() {
+			texts.push(frame.get_text());
+		}
+		assert_eq!(texts, ["^.\n..\n", "#>\n..\n", "##\n.v\n", "##\n..\n"]);
+	}
+
+	#[test]
+	fn draws_the_robot() {
+		let robot = get_robot();
+		let mut frame = create_frame(&robot.history);
+		let (white, black) = ([255, 255, 255], [0, 0, 0]);
+		frame.advance();
+		assert_eq!(frame.get_image(white, black).pixels, [white, ROBOT_COLOR, black, black]);
+		while frame.advance() {}
+		assert_eq!(frame.get_image(white, black).pixels, [white, white, black, black]);
+	}
+}
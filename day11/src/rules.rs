@@ -0,0 +1,95 @@
+use crate::driver::Controller;
+use common::vm::BigByte;
+use std::collections::HashMap;
+
+/*
+ * Turmite controller: a rule table telling, for the current state and the
+ * color read, which color to paint, where to turn and the next state. With a
+ * single state this is Langton's ant. The file has one rule per line:
+ *
+ *   state color paint turn next
+ *
+ * colors being `black` or `white`, turns `left` or `right`, and states any
+ * number, the first rule's state being the initial one. `#` starts a comment.
+ */
+pub struct Rules {
+	table: HashMap<(u32, BigByte), (BigByte, BigByte, u32)>,
+	state: u32,
+}
+
+fn get_color(word: &str) -> Result<BigByte, String> {
+	match word {
+		"black" | "0" => Ok(0),
+		"white" | "1" => Ok(1),
+		_ => Err(format!("invalid color {}", word)),
+	}
+}
+
+fn get_turn(word: &str) -> Result<BigByte, String> {
+	match word {
+		"left" | "L" => Ok(0),
+		"right" | "R" => Ok(1),
+		_ => Err(format!("invalid turn {}", word)),
+	}
+}
+
+fn get_state(word: &str) -> Result<u32, String> {
+	word.parse().map_err(|_| format!("invalid state {}", word))
+}
+
+pub fn parse_rules(text: &str) -> Result<Rules, String> {
+	let mut table = HashMap::new();
+	let mut initial = None;
+	for (i, line) in text.lines().enumerate() {
+		let line = line.split('#').next().unwrap_or("");
+		let words: Vec<&str> = line.split_whitespace().collect();
+		if words.is_empty() {
+			continue;
+		}
+		let at_line = |e: String| format!("line {}: {}", i + 1, e);
+		if words.len() != 5 {
+			return Err(at_line(String::from("expected state color paint turn next")));
+		}
+		let state = get_state(words[0]).map_err(at_line)?;
+		let color = get_color(words[1]).map_err(at_line)?;
+		let rule = (
+			get_color(words[2]).map_err(at_line)?,
+			get_turn(words[3]).map_err(at_line)?,
+			get_state(words[4]).map_err(at_line)?,
+		);
+		if table.insert((state, color), rule).is_some() {
+			return Err(at_line(String::from("rule defined twice")));
+		}
+		initial.get_or_insert(state);
+	}
+	match initial {
+		Some(state) => Ok(Rules { table, state }),
+		None => Err(String::from("no rules")),
+	}
+}
+
+pub fn load_rules(filename: &str) -> Result<Rules, String> {
+	let text = std::fs::read_to_string(filename).map_err(|e| format!("Could not read {}: {}", filename, e))?;
+	parse_rules(&text).map_err(|e| format!("Invalid rules {}: {}", filename, e))
+}
+
+impl Controller for Rules {
+	type State = u32;
+
+	fn react(&mut self, input: BigByte) -> Result<Option<Vec<BigByte>>, &'static str> {
+		let (paint, turn, next) = *self
+			.table
+			.get(&(self.state, input))
+			.ok_or("No rule for the current state and color")?;
+		self.state = next;
+		Ok(Some(vec![paint, turn]))
+	}
+
+	fn get_fingerprint(&self) -> Option<u64> {
+		Some(self.state as u64)
+	}
+
+	fn get_state(&self) -> Option<u32> {
+		Some(self.state)
+	}
+}
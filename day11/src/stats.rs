@@ -0,0 +1,169 @@
+use crate::image;
+use crate::PanelColor;
+use common::grid::{Bounds, Grid, Point, SparseGrid};
+
+#[derive(Clone, Copy, Default)]
+pub struct PanelStats {
+	// Paint orders received, whatever the color.
+	pub painted: u32,
+	// Times the robot arrived on the panel, its start counting as one.
+	pub visited: u32,
+	// Paint orders that changed the color of the panel.
+	pub flipped: u32,
+}
+
+pub type Statistics = SparseGrid<PanelStats>;
+
+fn update(stats: &mut Statistics, position: Point, change: impl FnOnce(&mut PanelStats)) {
+	let mut panel = stats.get(position).cloned().unwrap_or_default();
+	change(&mut panel);
+	stats.set(position, panel);
+}
+
+pub fn record_visit(stats: &mut Statistics, position: Point) {
+	update(stats, position, |panel| panel.visited += 1);
+}
+
+pub fn record_paint(stats: &mut Statistics, position: Point, flipped: bool) {
+	update(stats, position, |panel| {
+		panel.painted += 1;
+		panel.flipped += flipped as u32;
+	});
+}
+
+pub struct Summary {
+	pub panels_painted: usize,
+	pub paints: u32,
+	pub flips: u32,
+	pub visits: u32,
+	pub most_visited: u32,
+}
+
+impl std::fmt::Display for Summary {
+	fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+		write!(
+			f,
+			"{} panels painted, {} paint orders, {} flips, {} visits, at most {} on one panel",
+			self.panels_painted, self.paints, self.flips, self.visits, self.most_visited
+		)
+	}
+}
+
+pub fn summarize(stats: &Statistics) -> Summary {
+	let mut summary = Summary {
+		panels_painted: 0,
+		paints: 0,
+		flips: 0,
+		visits: 0,
+		most_visited: 0,
+	};
+	for (_, panel) in stats.iter() {
+		summary.panels_painted += (panel.painted > 0) as usize;
+		summary.paints += panel.painted;
+		summary.flips += panel.flipped;
+		summary.visits += panel.visited;
+		summary.most_visited = summary.most_visited.max(panel.visited);
+	}
+	summary
+}
+
+// One line per panel the robot went on, row by row, with its final color.
+fn get_csv(stats: &Statistics, hull: &SparseGrid<PanelColor>) -> String {
+	let mut positions: Vec<&Point> = stats.iter().map(|(position, _)| position).collect();
+	positions.sort_by_key(|position| (position.y, position.x));
+	let mut csv = String::from("x,y,painted,visited,flipped,color\n");
+	for position in positions {
+		let panel = stats.get(*position).unwrap();
+		let color = match hull.get(*position) {
+			Some(PanelColor::White) => "white",
+			_ => "black",
+		};
+		csv += &format!(
+			"{},{},{},{},{},{}\n",
+			position.x, position.y, panel.painted, panel.visited, panel.flipped, color
+		);
+	}
+	csv
+}
+
+pub fn write_csv(stats: &Statistics, hull: &SparseGrid<PanelColor>, filename: &str) -> Result<(), String> {
+	std::fs::write(filename, get_csv(stats, hull)).map_err(|e| format!("Could not write {}: {}", filename, e))
+}
+
+// Black for unvisited panels, then red, yellow and white as visits grow.
+fn get_heat(visits: u32, most_visited: u32) -> image::Color {
+	if visits == 0 {
+		return [0, 0, 0];
+	}
+	let heat = visits as f32 / most_visited as f32 * 3.0;
+	let channel = |start: f32| ((heat - start).clamp(0.0, 1.0) * 255.0) as u8;
+	[channel(0.0), channel(1.0), channel(2.0)]
+}
+
+pub fn get_heatmap(stats: &Statistics, bounds: &Bounds) -> image::Image {
+	let most_visited = summarize(stats).most_visited;
+	let rows = stats.render(bounds, |_, panel| {
+		get_heat(panel.map_or(0, |panel| panel.visited), most_visited)
+	});
+	image::from_rows(&rows)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::create_lone_robot;
+
+	// Goes round a square painting white, then paints a white panel white and another one black.
+	#[test]
+	fn counts_paints_and_flips() {
+		let mut orders = vec![vec![0, 1], vec![1, 1], vec![1, 1], vec![1, 1], vec![1, 1], vec![1, 1]];
+		let mut script = |_| orders.pop();
+		let mut robot = create_lone_robot(PanelColor::Black);
+		robot.control(&mut script, None).unwrap();
+		let summary = summarize(&robot.stats);
+		assert_eq!(
+			(summary.panels_painted, summary.paints, summary.flips, summary.visits, summary.most_visited),
+			(4, 6, 5, 7, 2)
+		);
+		let get = |x, y| {
+			let panel = robot.stats.get(Point::new(x, y)).unwrap();
+			(panel.painted, panel.visited, panel.flipped)
+		};
+		assert_eq!(get(0, 0), (2, 2, 1));
+		assert_eq!(get(1, 0), (2, 2, 2));
+		assert_eq!(get(0, 1), (1, 1, 1));
+	}
+
+	fn get_stats() -> Statistics {
+		let mut stats = Statistics::new();
+		record_visit(&mut stats, Point::new(1, 0));
+		record_paint(&mut stats, Point::new(1, 0), true);
+		record_visit(&mut stats, Point::new(0, 1));
+		record_visit(&mut stats, Point::new(0, 1));
+		record_paint(&mut stats, Point::new(0, 1), false);
+		record_visit(&mut stats, Point::new(0, 1));
+		stats
+	}
+
+	#[test]
+	fn writes_csv() {
+		let mut hull = SparseGrid::new();
+		hull.set(Point::new(1, 0), PanelColor::White);
+		hull.set(Point::new(0, 1), PanelColor::Black);
+		assert_eq!(
+			get_csv(&get_stats(), &hull),
+			"x,y,painted,visited,flipped,color\n1,0,1,1,1,white\n0,1,1,3,0,black\n"
+		);
+	}
+
+	#[test]
+	fn draws_heatmap() {
+		let mut stats = get_stats();
+		record_visit(&mut stats, Point::new(1, 1));
+		record_visit(&mut stats, Point::new(1, 1));
+		let bounds = stats.bounds().unwrap();
+		let heatmap = get_heatmap(&stats, &bounds);
+		assert_eq!((heatmap.width, heatmap.height), (2, 2));
+		assert_eq!(heatmap.pixels, [[0, 0, 0], [255, 0, 0], [255, 255, 255], [255, 255, 0]]);
+	}
+}